@@ -0,0 +1,215 @@
+//! Benchmarks for the render and texture-update hot paths.
+//!
+//! All benchmarks run on a WARP (software rasterizer) device rendering into an
+//! offscreen texture, so they do not need a GPU or a window and can run on any
+//! Windows CI machine with `cargo bench`.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use egui::{
+    epaint::{ClippedShape, ImageDelta, Shape},
+    pos2, vec2, Color32, ColorImage, FontImage, ImageData, Rect, TextureId,
+    TextureOptions, TexturesDelta,
+};
+
+use windows::Win32::Graphics::{Direct3D10::*, Dxgi::Common::*};
+
+const FRAME_WIDTH: u32 = 1280;
+const FRAME_HEIGHT: u32 = 720;
+
+/// A WARP device with an offscreen render target, an `egui` context whose
+/// fonts are already uploaded and a renderer.
+struct Harness {
+    device: ID3D10Device,
+    render_target: ID3D10RenderTargetView,
+    egui_ctx: egui::Context,
+    renderer: egui_directx10::Renderer,
+}
+
+impl Harness {
+    fn new() -> egui_directx10::Result<Self> {
+        let device = egui_directx10::create_warp_device()?;
+        let render_target = create_render_target(&device)?;
+        let egui_ctx = egui::Context::default();
+        let renderer = egui_directx10::Renderer::new(&device, false)?;
+        let mut harness = Self {
+            device,
+            render_target,
+            egui_ctx,
+            renderer,
+        };
+        // the first frame uploads the font atlas.
+        let output = harness.run(|_| ());
+        harness.render(output);
+        Ok(harness)
+    }
+
+    fn run(
+        &self,
+        ui: impl FnMut(&egui::Context),
+    ) -> egui_directx10::RendererOutput {
+        let egui_input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                egui::Pos2::ZERO,
+                vec2(FRAME_WIDTH as _, FRAME_HEIGHT as _),
+            )),
+            ..Default::default()
+        };
+        egui_directx10::split_output(self.egui_ctx.run(egui_input, ui)).renderer
+    }
+
+    fn render(&mut self, output: egui_directx10::RendererOutput) {
+        self.renderer
+            .render(
+                &self.device,
+                &self.render_target,
+                &self.egui_ctx,
+                output,
+                1.0,
+            )
+            .expect("failed to render");
+        unsafe { self.device.Flush() };
+    }
+}
+
+fn create_render_target(
+    device: &ID3D10Device,
+) -> windows::core::Result<ID3D10RenderTargetView> {
+    let texture = unsafe {
+        device.CreateTexture2D(
+            &D3D10_TEXTURE2D_DESC {
+                Width: FRAME_WIDTH,
+                Height: FRAME_HEIGHT,
+                MipLevels: 1,
+                ArraySize: 1,
+                Format: DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Usage: D3D10_USAGE_DEFAULT,
+                BindFlags: D3D10_BIND_RENDER_TARGET.0 as _,
+                ..Default::default()
+            },
+            None,
+        )
+    }?;
+    let mut render_target = None;
+    unsafe {
+        device.CreateRenderTargetView(&texture, None, Some(&mut render_target))
+    }?;
+    Ok(render_target.unwrap())
+}
+
+/// `count` rounded rectangles, each with its own clip rect so that every one
+/// of them ends up in a separate mesh.
+fn separate_meshes(count: usize) -> Vec<ClippedShape> {
+    (0..count)
+        .map(|i| {
+            let rect = Rect::from_min_size(
+                pos2((i % 32) as f32 * 40.0, (i / 32 % 18) as f32 * 40.0),
+                vec2(32.0, 32.0),
+            );
+            ClippedShape {
+                clip_rect: rect,
+                shape: Shape::rect_filled(
+                    rect,
+                    4.0,
+                    Color32::from_rgb(i as u8, 128, 255 - i as u8),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Replace `count` color textures and patch the font atlas, like a frame in an
+/// image-heavy UI does.
+fn texture_churn(count: u64) -> TexturesDelta {
+    let mut set = (0..count)
+        .map(|i| {
+            (
+                TextureId::Managed(1 + i),
+                ImageDelta::full(
+                    ImageData::Color(Arc::new(ColorImage::new(
+                        [256, 256],
+                        Color32::from_gray(i as u8),
+                    ))),
+                    TextureOptions::default(),
+                ),
+            )
+        })
+        .collect::<Vec<_>>();
+    set.push((
+        TextureId::default(),
+        ImageDelta::partial(
+            [0, 0],
+            ImageData::Font(FontImage::new([64, 64])),
+            TextureOptions::default(),
+        ),
+    ));
+    TexturesDelta {
+        set,
+        free: Vec::new(),
+    }
+}
+
+fn bench_meshes(c: &mut Criterion) {
+    let mut harness = Harness::new().expect("failed to set up benchmark");
+    for count in [16, 256] {
+        let shapes = separate_meshes(count);
+        c.bench_function(&format!("render {count} meshes"), |b| {
+            b.iter_batched(
+                || egui_directx10::RendererOutput {
+                    textures_delta: TexturesDelta::default(),
+                    shapes: shapes.clone(),
+                    pixels_per_point: 1.0,
+                },
+                |output| harness.render(output),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+fn bench_texture_churn(c: &mut Criterion) {
+    let mut harness = Harness::new().expect("failed to set up benchmark");
+    c.bench_function("texture churn", |b| {
+        b.iter_batched(
+            || egui_directx10::RendererOutput {
+                textures_delta: texture_churn(8),
+                shapes: Vec::new(),
+                pixels_per_point: 1.0,
+            },
+            |output| harness.render(output),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_static_ui(c: &mut Criterion) {
+    let mut harness = Harness::new().expect("failed to set up benchmark");
+    let mut demo = egui_demo_lib::DemoWindows::default();
+    // let the layout settle, then replay the same frame over and over.
+    let mut shapes = Vec::new();
+    for _ in 0..3 {
+        let output = harness.run(|ctx| demo.ui(ctx));
+        shapes.clone_from(&output.shapes);
+        harness.render(output);
+    }
+    c.bench_function("static ui replay", |b| {
+        b.iter_batched(
+            || egui_directx10::RendererOutput {
+                textures_delta: TexturesDelta::default(),
+                shapes: shapes.clone(),
+                pixels_per_point: 1.0,
+            },
+            |output| harness.render(output),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_meshes, bench_texture_churn, bench_static_ui);
+criterion_main!(benches);
@@ -82,10 +82,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         let egui_output = egui_ctx.run(egui_input, |ctx| {
                             egui_demo.ui(ctx);
                         });
-                        let (renderer_output, platform_output, _) =
+                        let egui_output =
                             egui_directx10::split_output(egui_output);
-                        egui_winit
-                            .handle_platform_output(&window, platform_output);
+                        egui_winit.handle_platform_output(
+                            &window,
+                            egui_output.platform,
+                        );
 
                         unsafe {
                             device.ClearRenderTargetView(
@@ -97,7 +99,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             &device,
                             render_target,
                             &egui_ctx,
-                            renderer_output,
+                            egui_output.renderer,
                             window.scale_factor() as _,
                         );
                         let _ =
@@ -0,0 +1,398 @@
+//! Renders the `egui` demo into an offscreen texture, blurs it and feeds it
+//! back into a second `egui` pass, which draws it as the background of a
+//! "frosted glass" panel.
+//!
+//! The blur is done by generating mipmaps for the offscreen texture and
+//! sampling one of its smaller mip levels, which keeps this example free of
+//! custom shaders. A real application would run its own blur pass instead.
+
+use windows::Win32::{
+    Foundation::{BOOL, HWND},
+    Graphics::{
+        Direct3D::*,
+        Direct3D10::*,
+        Dxgi::{Common::*, *},
+    },
+};
+
+use winit::{
+    dpi::PhysicalSize,
+    event::{Event, WindowEvent},
+    event_loop::EventLoop,
+    raw_window_handle::{HasWindowHandle, RawWindowHandle},
+    window::WindowBuilder,
+};
+
+use egui::{pos2, Color32, Rect};
+
+/// The mip level sampled for the blurred background.
+const BLUR_MIP: u32 = 3;
+
+/// The offscreen texture the first pass renders into, with views of its
+/// sharp and blurred versions.
+struct SceneTarget {
+    render_target: ID3D10RenderTargetView,
+    mips: ID3D10ShaderResourceView,
+    sharp: ID3D10ShaderResourceView,
+    blurred: ID3D10ShaderResourceView,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    pretty_env_logger::init();
+
+    let event_loop = EventLoop::new()?;
+    let window = WindowBuilder::new()
+        .with_title("egui-directx10 feedback")
+        .with_inner_size(PhysicalSize::new(1600, 900))
+        .build(&event_loop)?;
+    let hwnd =
+        if let RawWindowHandle::Win32(raw) = window.window_handle()?.as_raw() {
+            HWND(raw.hwnd.get() as _)
+        } else {
+            panic!("unexpected RawWindowHandle variant");
+        };
+
+    let frame_size = window.inner_size();
+    let (device, swap_chain) = create_device_and_swap_chain(
+        hwnd,
+        frame_size.width,
+        frame_size.height,
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+    )?;
+    let mut render_target =
+        Some(create_render_target_for_swap_chain(&device, &swap_chain)?);
+
+    // Each `egui::Context` needs its own renderer, as every context uses the
+    // same texture ids for its own font atlas.
+    let scene_ctx = egui::Context::default();
+    let overlay_ctx = egui::Context::default();
+    let mut scene_renderer = egui_directx10::Renderer::new(&device, false)?;
+    let mut overlay_renderer = egui_directx10::Renderer::new(&device, false)?;
+
+    let mut scene =
+        create_scene_target(&device, frame_size.width, frame_size.height)?;
+    let mut scene_textures = (
+        overlay_renderer.register_native_texture(scene.sharp.clone()),
+        overlay_renderer.register_native_texture(scene.blurred.clone()),
+    );
+
+    let mut egui_winit = egui_winit::State::new(
+        scene_ctx.clone(),
+        scene_ctx.viewport_id(),
+        &window,
+        None,
+        None,
+    );
+    let mut egui_demo = egui_demo_lib::DemoWindows::default();
+
+    event_loop.run(move |event, event_loop| match event {
+        Event::AboutToWait => window.request_redraw(),
+        Event::WindowEvent { window_id, event } => {
+            if window_id != window.id() {
+                return;
+            }
+            if egui_winit.on_window_event(&window, &event).consumed {
+                return;
+            }
+            match event {
+                WindowEvent::CloseRequested => event_loop.exit(),
+                WindowEvent::Resized(PhysicalSize {
+                    width: new_width,
+                    height: new_height,
+                }) => {
+                    if let Err(err) = resize_swap_chain_and_render_target(
+                        &device,
+                        &swap_chain,
+                        &mut render_target,
+                        new_width,
+                        new_height,
+                        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+                    ) {
+                        panic!("fail to resize framebuffers: {err:?}");
+                    }
+                    overlay_renderer.free_user_texture(scene_textures.0);
+                    overlay_renderer.free_user_texture(scene_textures.1);
+                    scene = create_scene_target(&device, new_width, new_height)
+                        .expect("fail to resize the offscreen texture");
+                    scene_textures = (
+                        overlay_renderer
+                            .register_native_texture(scene.sharp.clone()),
+                        overlay_renderer
+                            .register_native_texture(scene.blurred.clone()),
+                    );
+                },
+                WindowEvent::RedrawRequested => {
+                    let Some(render_target) = &render_target else {
+                        unreachable!()
+                    };
+                    let scale_factor = window.scale_factor() as f32;
+
+                    // the overlay is not interactive, it only needs to know
+                    // the size and scale of the window.
+                    let egui_input = egui_winit.take_egui_input(&window);
+                    let overlay_input = egui::RawInput {
+                        screen_rect: egui_input.screen_rect,
+                        viewports: egui_input.viewports.clone(),
+                        time: egui_input.time,
+                        ..Default::default()
+                    };
+
+                    // first pass: the demo into the offscreen texture.
+                    let scene_output = scene_ctx.run(egui_input, |ctx| {
+                        egui_demo.ui(ctx);
+                    });
+                    let scene_output =
+                        egui_directx10::split_output(scene_output);
+                    egui_winit
+                        .handle_platform_output(&window, scene_output.platform);
+                    unsafe {
+                        device.ClearRenderTargetView(
+                            &scene.render_target,
+                            &[0.0, 0.0, 0.0, 1.0],
+                        );
+                    }
+                    let _ = scene_renderer.render(
+                        &device,
+                        &scene.render_target,
+                        &scene_ctx,
+                        scene_output.renderer,
+                        scale_factor,
+                    );
+
+                    // blur it, after unbinding it as render target.
+                    unsafe {
+                        device.OMSetRenderTargets(None, None);
+                        device.GenerateMips(&scene.mips);
+                    }
+
+                    // second pass: sample the result into the swap chain.
+                    let overlay_output =
+                        overlay_ctx.run(overlay_input, |ctx| {
+                            overlay_ui(ctx, scene_textures.0, scene_textures.1);
+                        });
+                    unsafe {
+                        device.ClearRenderTargetView(
+                            render_target,
+                            &[0.0, 0.0, 0.0, 1.0],
+                        );
+                    }
+                    let _ = overlay_renderer.render(
+                        &device,
+                        render_target,
+                        &overlay_ctx,
+                        egui_directx10::split_output(overlay_output).renderer,
+                        scale_factor,
+                    );
+                    // unbind the offscreen texture before it becomes the
+                    // render target of the next frame.
+                    unsafe { device.PSSetShaderResources(0, Some(&[None])) };
+                    let _ = unsafe { swap_chain.Present(1, DXGI_PRESENT(0)) };
+                },
+                _ => (),
+            }
+        },
+        _ => (),
+    })?;
+    Ok(())
+}
+
+fn overlay_ui(
+    ctx: &egui::Context,
+    sharp: egui::TextureId,
+    blurred: egui::TextureId,
+) {
+    let screen = ctx.screen_rect();
+    let full_uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+    ctx.layer_painter(egui::LayerId::background()).image(
+        sharp,
+        screen,
+        full_uv,
+        Color32::WHITE,
+    );
+
+    let panel = Rect::from_min_max(
+        pos2(screen.max.x - 360.0, screen.min.y + 40.0),
+        pos2(screen.max.x - 40.0, screen.max.y - 40.0),
+    );
+    let panel_uv = Rect::from_min_max(
+        (panel.min.to_vec2() / screen.size()).to_pos2(),
+        (panel.max.to_vec2() / screen.size()).to_pos2(),
+    );
+    egui::Area::new(egui::Id::new("frosted glass"))
+        .fixed_pos(panel.min)
+        .show(ctx, |ui| {
+            ui.painter().image(
+                blurred,
+                panel,
+                panel_uv,
+                Color32::from_gray(200),
+            );
+            ui.set_min_size(panel.size());
+            ui.vertical_centered(|ui| {
+                ui.add_space(16.0);
+                ui.heading("Frosted glass");
+                ui.label("The blurred UI behind this panel is sampled from");
+                ui.label("the output of the previous render pass.");
+            });
+        });
+}
+
+fn create_scene_target(
+    device: &ID3D10Device,
+    width: u32,
+    height: u32,
+) -> windows::core::Result<SceneTarget> {
+    // the blurred mip level has to exist even for tiny windows.
+    let min_size = 1 << BLUR_MIP;
+    let texture = unsafe {
+        device.CreateTexture2D(
+            &D3D10_TEXTURE2D_DESC {
+                Width: width.max(min_size),
+                Height: height.max(min_size),
+                MipLevels: 0,
+                ArraySize: 1,
+                Format: DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Usage: D3D10_USAGE_DEFAULT,
+                BindFlags: (D3D10_BIND_RENDER_TARGET.0
+                    | D3D10_BIND_SHADER_RESOURCE.0)
+                    as _,
+                CPUAccessFlags: 0,
+                MiscFlags: D3D10_RESOURCE_MISC_GENERATE_MIPS.0 as _,
+            },
+            None,
+        )
+    }?;
+
+    let mut render_target = None;
+    unsafe {
+        device.CreateRenderTargetView(&texture, None, Some(&mut render_target))
+    }?;
+    let create_srv = |most_detailed_mip, mip_levels| {
+        let mut srv = None;
+        unsafe {
+            device.CreateShaderResourceView(
+                &texture,
+                Some(&D3D10_SHADER_RESOURCE_VIEW_DESC {
+                    Format: DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+                    ViewDimension: D3D10_SRV_DIMENSION_TEXTURE2D,
+                    Anonymous: D3D10_SHADER_RESOURCE_VIEW_DESC_0 {
+                        Texture2D: D3D10_TEX2D_SRV {
+                            MostDetailedMip: most_detailed_mip,
+                            MipLevels: mip_levels,
+                        },
+                    },
+                }),
+                Some(&mut srv),
+            )
+        }
+        .map(|()| srv.unwrap())
+    };
+    Ok(SceneTarget {
+        render_target: render_target.unwrap(),
+        mips: create_srv(0, u32::MAX)?,
+        sharp: create_srv(0, 1)?,
+        blurred: create_srv(BLUR_MIP, 1)?,
+    })
+}
+
+fn resize_swap_chain_and_render_target(
+    device: &ID3D10Device,
+    swap_chain: &IDXGISwapChain,
+    render_target: &mut Option<ID3D10RenderTargetView>,
+    new_width: u32,
+    new_height: u32,
+    new_format: DXGI_FORMAT,
+) -> windows::core::Result<()> {
+    render_target.take();
+    unsafe {
+        swap_chain.ResizeBuffers(
+            2,
+            new_width,
+            new_height,
+            new_format,
+            DXGI_SWAP_CHAIN_FLAG(0),
+        )
+    }?;
+    render_target
+        .replace(create_render_target_for_swap_chain(device, swap_chain)?);
+    Ok(())
+}
+
+fn create_device_and_swap_chain(
+    window: HWND,
+    frame_width: u32,
+    frame_height: u32,
+    frame_format: DXGI_FORMAT,
+) -> windows::core::Result<(ID3D10Device, IDXGISwapChain)> {
+    let dxgi_factory: IDXGIFactory = unsafe { CreateDXGIFactory() }?;
+    let dxgi_adapter: IDXGIAdapter = unsafe { dxgi_factory.EnumAdapters(0) }?;
+
+    let mut device = None;
+    unsafe {
+        D3D10CreateDevice(
+            &dxgi_adapter,
+            D3D10_DRIVER_TYPE_HARDWARE,
+            None,
+            if cfg!(debug_assertions) {
+                D3D10_CREATE_DEVICE_DEBUG.0 as _
+            } else {
+                0
+            },
+            D3D10_SDK_VERSION,
+            Some(&mut device),
+        )
+    }?;
+    let device = device.unwrap();
+
+    let swap_chain_desc = DXGI_SWAP_CHAIN_DESC {
+        BufferDesc: DXGI_MODE_DESC {
+            Width: frame_width,
+            Height: frame_height,
+            Format: frame_format,
+            ..DXGI_MODE_DESC::default()
+        },
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+        BufferCount: 1,
+        OutputWindow: window,
+        Windowed: BOOL(1),
+        SwapEffect: DXGI_SWAP_EFFECT_DISCARD,
+        Flags: 0,
+    };
+
+    let mut swap_chain = None;
+    unsafe {
+        dxgi_factory.CreateSwapChain(&device, &swap_chain_desc, &mut swap_chain)
+    }
+    .ok()?;
+    let swap_chain = swap_chain.unwrap();
+
+    unsafe {
+        dxgi_factory.MakeWindowAssociation(window, DXGI_MWA_NO_ALT_ENTER)
+    }?;
+    Ok((device, swap_chain))
+}
+
+fn create_render_target_for_swap_chain(
+    device: &ID3D10Device,
+    swap_chain: &IDXGISwapChain,
+) -> windows::core::Result<ID3D10RenderTargetView> {
+    let swap_chain_texture =
+        unsafe { swap_chain.GetBuffer::<ID3D10Texture2D>(0) }?;
+    let mut render_target = None;
+    unsafe {
+        device.CreateRenderTargetView(
+            &swap_chain_texture,
+            None,
+            Some(&mut render_target),
+        )
+    }?;
+    Ok(render_target.unwrap())
+}
@@ -1,3 +1,25 @@
+// 0-1 linear  from  0-1 sRGB gamma
+// the inverse of gamma_from_linear_rgb, as in egui-wgpu's egui.wgsl
+float3 linear_from_gamma_rgb(float3 srgb) {
+    float3 cutoff = step(srgb, float3(0.04045, 0.04045, 0.04045));
+    float3 lower = srgb / float3(12.92, 12.92, 12.92);
+    float3 higher = pow(abs((srgb + float3(0.055, 0.055, 0.055)) / float3(1.055, 1.055, 1.055)), float3(2.4, 2.4, 2.4));
+    return lerp(higher, lower, cutoff);
+}
+
+cbuffer Transform: register(b0) {
+    // scale in xy and offset in zw from physical pixels to NDC
+    float4 g_ndc_from_pixels;
+    float  g_pixels_per_point;
+    // 1.0 to round positions to whole pixels, 0.0 otherwise
+    float  g_snap_to_pixels;
+    // premultiplied linear color multiplied into every vertex color, with
+    // the rgb scaled to the white level for HDR output
+    float4 g_tint;
+};
+
+// i_pos is in egui points
+// i_color is the premultiplied sRGB Color32 of egui, normalized to 0-1
 void vs_main(
     in const float2 i_pos  : POSITION,
     in const float2 i_uv   : TEXCOORD,
@@ -5,9 +27,11 @@ void vs_main(
     out      float4 o_pos  : SV_POSITION,
     out      float2 o_uv   : TEXCOORD,
     out      float4 o_color: COLOR) {
-    o_pos   = float4(i_pos, 0.0, 1.0);
+    float2 pixels = i_pos * g_pixels_per_point;
+    pixels = lerp(pixels, round(pixels), g_snap_to_pixels);
+    o_pos   = float4(pixels * g_ndc_from_pixels.xy + g_ndc_from_pixels.zw, 0.0, 1.0);
     o_uv    = i_uv;
-    o_color = i_color;
+    o_color = float4(linear_from_gamma_rgb(i_color.rgb), i_color.a) * g_tint;
 }
 
 Texture2D<float4> g_tex    : register(t0);
@@ -22,17 +46,32 @@ float3 gamma_from_linear_rgb(float3 rgb) {
     return lerp(higher, lower, cutoff);
 }
 
-float4 ps_main_gamma(
+// the same color is written to every render target slot, so that all the
+// render targets bound receive the output. unbound slots are discarded.
+struct PsOutput {
+    float4 color[8]: SV_TARGET;
+};
+
+PsOutput to_all_targets(float4 color) {
+    PsOutput output;
+    [unroll] for (int i = 0; i < 8; i++) {
+        output.color[i] = color;
+    }
+    return output;
+}
+
+PsOutput ps_main_gamma(
     in const float4 i_pos  : SV_POSITION,
     in const float2 i_uv   : TEXCOORD,
-    in const float4 i_color: COLOR): SV_TARGET {
+    in const float4 i_color: COLOR) {
     float4 linearColor = i_color * g_tex.Sample(g_sampler, i_uv);
-    return float4(gamma_from_linear_rgb(linearColor.rgb), linearColor.a);
+    return to_all_targets(
+        float4(gamma_from_linear_rgb(linearColor.rgb), linearColor.a));
 }
 
-float4 ps_main_linear(
+PsOutput ps_main_linear(
     in const float4 i_pos  : SV_POSITION,
     in const float2 i_uv   : TEXCOORD,
-    in const float4 i_color: COLOR): SV_TARGET {
-    return i_color * g_tex.Sample(g_sampler, i_uv);
+    in const float4 i_color: COLOR) {
+    return to_all_targets(i_color * g_tex.Sample(g_sampler, i_uv));
 }
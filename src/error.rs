@@ -0,0 +1,82 @@
+use windows::Win32::Graphics::Dxgi::{
+    DXGI_ERROR_DEVICE_HUNG, DXGI_ERROR_DEVICE_REMOVED, DXGI_ERROR_DEVICE_RESET,
+};
+
+/// An error of the [`Renderer`](crate::Renderer).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The device has been removed or reset, e.g. because the driver was
+    /// updated or crashed. It cannot be used anymore, so create a new one and
+    /// call [`Renderer::reset_device`](crate::Renderer::reset_device). Holds
+    /// the reason reported by the device.
+    DeviceLost(windows::core::Error),
+    /// Creating a Direct3D10 resource failed, e.g. because the device ran
+    /// out of memory.
+    CreateResource {
+        /// What was being created, e.g. `"vertex shader"`.
+        resource: &'static str,
+        /// The error returned by the device.
+        source: windows::core::Error,
+    },
+    /// The number of physical pixels per point to render with, i.e. the
+    /// scale factor times the zoom factor, is not a positive finite number.
+    InvalidScaleFactor(f32),
+    /// An argument cannot be used by the renderer, e.g. a viewport outside
+    /// of the render target. Holds a description of the problem.
+    InvalidArgument(String),
+    /// Any other failed Direct3D10 or DXGI call. The device is still usable.
+    Windows(windows::core::Error),
+}
+
+/// The result of the fallible functions of this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wrap an error returned while creating `resource`.
+    pub(crate) fn create(
+        resource: &'static str,
+    ) -> impl FnOnce(windows::core::Error) -> Self {
+        move |source| match Self::from(source) {
+            Self::Windows(source) => Self::CreateResource { resource, source },
+            error => error,
+        }
+    }
+}
+
+impl From<windows::core::Error> for Error {
+    fn from(error: windows::core::Error) -> Self {
+        match error.code() {
+            DXGI_ERROR_DEVICE_REMOVED
+            | DXGI_ERROR_DEVICE_RESET
+            | DXGI_ERROR_DEVICE_HUNG => Self::DeviceLost(error),
+            _ => Self::Windows(error),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DeviceLost(reason) => write!(f, "device lost: {reason}"),
+            Self::CreateResource { resource, source } => {
+                write!(f, "failed to create the {resource}: {source}")
+            },
+            Self::InvalidScaleFactor(pixels_per_point) => {
+                write!(f, "invalid pixels per point {pixels_per_point}")
+            },
+            Self::InvalidArgument(message) => f.write_str(message),
+            Self::Windows(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::DeviceLost(error)
+            | Self::CreateResource { source: error, .. }
+            | Self::Windows(error) => Some(error),
+            Self::InvalidScaleFactor(_) | Self::InvalidArgument(_) => None,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hasher},
+    mem,
+};
+
+use windows::Win32::Graphics::{Direct3D10::*, Dxgi::Common::*};
+
+use crate::{set_debug_name, Error, PreparedMesh, Result};
+
+/// How many frames an entry is kept without being drawn before it is evicted.
+/// More than one, so that rendering more than one `egui` output per frame, or
+/// a UI alternating between a few states, does not keep rebuilding buffers.
+const MAX_UNUSED_FRAMES: u64 = 8;
+
+struct Entry {
+    vertex_buffer: ID3D10Buffer,
+    index_buffer: ID3D10Buffer,
+    index_format: DXGI_FORMAT,
+    /// The frame the entry was last drawn in.
+    last_used: u64,
+}
+
+/// Immutable vertex and index buffers of the meshes drawn recently, keyed by
+/// a hash of their vertices and indices, see
+/// [`Renderer::set_geometry_cache`](crate::Renderer::set_geometry_cache).
+#[derive(Default)]
+pub struct GeometryCache {
+    entries: HashMap<u64, Entry>,
+    frame: u64,
+}
+
+impl GeometryCache {
+    /// Get the buffers holding the vertices and indices of `meshes` one after
+    /// another, creating them if the same meshes were not drawn recently.
+    /// Returns them along with the format to bind the index buffer with.
+    pub fn get_or_create(
+        &mut self,
+        device: &ID3D10Device,
+        meshes: &[PreparedMesh],
+        debug_names: bool,
+    ) -> Result<(ID3D10Buffer, ID3D10Buffer, DXGI_FORMAT)> {
+        let key = Self::hash(meshes);
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.frame;
+            return Ok((
+                entry.vertex_buffer.clone(),
+                entry.index_buffer.clone(),
+                entry.index_format,
+            ));
+        }
+
+        let vertices = meshes
+            .iter()
+            .flat_map(PreparedMesh::vertices)
+            .collect::<Vec<_>>();
+        let indices = || meshes.iter().flat_map(|mesh| mesh.indices);
+        let (index_buffer, index_format) = if indices()
+            .all(|&index| index <= u16::MAX as u32)
+        {
+            let indices =
+                indices().map(|&index| index as u16).collect::<Vec<_>>();
+            let buffer =
+                Self::create_buffer(device, D3D10_BIND_INDEX_BUFFER, &indices)?;
+            (buffer, DXGI_FORMAT_R16_UINT)
+        } else {
+            let indices = indices().copied().collect::<Vec<_>>();
+            let buffer =
+                Self::create_buffer(device, D3D10_BIND_INDEX_BUFFER, &indices)?;
+            (buffer, DXGI_FORMAT_R32_UINT)
+        };
+        let vertex_buffer =
+            Self::create_buffer(device, D3D10_BIND_VERTEX_BUFFER, &vertices)?;
+        if debug_names {
+            set_debug_name(&vertex_buffer, "egui cached vertex buffer")?;
+            set_debug_name(&index_buffer, "egui cached index buffer")?;
+        }
+
+        self.entries.insert(
+            key,
+            Entry {
+                vertex_buffer: vertex_buffer.clone(),
+                index_buffer: index_buffer.clone(),
+                index_format,
+                last_used: self.frame,
+            },
+        );
+        Ok((vertex_buffer, index_buffer, index_format))
+    }
+
+    /// Evict the entries not drawn in the last few frames, and start the next
+    /// frame.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        self.entries
+            .retain(|_, entry| frame - entry.last_used < MAX_UNUSED_FRAMES);
+        self.frame += 1;
+    }
+
+    /// Hash the vertices, indices and boundaries of `meshes`. A collision of
+    /// the 64-bit hash would draw stale geometry, which is unlikely enough
+    /// not to compare the content as well.
+    fn hash(meshes: &[PreparedMesh]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for mesh in meshes {
+            let vertices = &mesh.mesh.vertices;
+            hasher.write_usize(vertices.len());
+            hasher.write_usize(mesh.indices.len());
+            // `Vertex` is two `Pos2` and a `Color32`, without any padding.
+            hasher.write(unsafe {
+                std::slice::from_raw_parts(
+                    vertices.as_ptr() as *const u8,
+                    mem::size_of_val(vertices.as_slice()),
+                )
+            });
+            hasher.write(unsafe {
+                std::slice::from_raw_parts(
+                    mesh.indices.as_ptr() as *const u8,
+                    mem::size_of_val(mesh.indices),
+                )
+            });
+        }
+        hasher.finish()
+    }
+
+    fn create_buffer<T>(
+        device: &ID3D10Device,
+        bind_flag: D3D10_BIND_FLAG,
+        data: &[T],
+    ) -> Result<ID3D10Buffer> {
+        let mut buffer = None;
+        unsafe {
+            device.CreateBuffer(
+                &D3D10_BUFFER_DESC {
+                    ByteWidth: mem::size_of_val(data) as _,
+                    Usage: D3D10_USAGE_IMMUTABLE,
+                    BindFlags: bind_flag.0 as _,
+                    ..D3D10_BUFFER_DESC::default()
+                },
+                Some(&D3D10_SUBRESOURCE_DATA {
+                    pSysMem: data.as_ptr() as _,
+                    ..D3D10_SUBRESOURCE_DATA::default()
+                }),
+                Some(&mut buffer),
+            )
+        }
+        .map_err(Error::create(
+            if bind_flag == D3D10_BIND_VERTEX_BUFFER {
+                "cached vertex buffer"
+            } else {
+                "cached index buffer"
+            },
+        ))?;
+        Ok(buffer.unwrap())
+    }
+}
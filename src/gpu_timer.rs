@@ -0,0 +1,142 @@
+use std::{collections::VecDeque, mem, time::Duration};
+
+use windows::{
+    core::Interface,
+    Win32::{Foundation::S_OK, Graphics::Direct3D10::*},
+};
+
+use crate::{Error, Result};
+
+/// How many measurements may wait for the GPU at once. Frames rendered while
+/// that many are pending go unmeasured rather than piling up queries.
+const MAX_PENDING: usize = 4;
+
+/// The queries measuring one render call.
+struct Measurement {
+    disjoint: ID3D10Query,
+    begin: ID3D10Query,
+    end: ID3D10Query,
+}
+
+/// Timestamp queries around the draw calls of the renderer, see
+/// [`Renderer::set_gpu_timing`](crate::Renderer::set_gpu_timing).
+#[derive(Default)]
+pub struct GpuTimer {
+    /// Issued measurements, oldest first.
+    pending: VecDeque<Measurement>,
+    /// Resolved measurements, kept to be issued again.
+    free: Vec<Measurement>,
+    /// The measurement in between `begin` and `end`.
+    current: Option<Measurement>,
+    last: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// Issue the queries starting a measurement, unless too many are still
+    /// pending.
+    pub fn begin(&mut self, device: &ID3D10Device) -> Result<()> {
+        // a render call failing in between leaves its measurement open.
+        self.end();
+        self.poll();
+        if self.pending.len() >= MAX_PENDING {
+            return Ok(());
+        }
+        let measurement = match self.free.pop() {
+            Some(measurement) => measurement,
+            None => Measurement {
+                disjoint: Self::create_query(
+                    device,
+                    D3D10_QUERY_TIMESTAMP_DISJOINT,
+                )?,
+                begin: Self::create_query(device, D3D10_QUERY_TIMESTAMP)?,
+                end: Self::create_query(device, D3D10_QUERY_TIMESTAMP)?,
+            },
+        };
+        unsafe {
+            measurement.disjoint.Begin();
+            measurement.begin.End();
+        }
+        self.current = Some(measurement);
+        Ok(())
+    }
+
+    /// Issue the queries ending the measurement started by `begin`, if any.
+    pub fn end(&mut self) {
+        if let Some(measurement) = self.current.take() {
+            unsafe {
+                measurement.end.End();
+                measurement.disjoint.End();
+            }
+            self.pending.push_back(measurement);
+        }
+    }
+
+    /// Get the GPU time of the latest render call measured so far.
+    pub fn last(&mut self) -> Option<Duration> {
+        self.poll();
+        self.last
+    }
+
+    /// Collect the measurements the GPU is done with, without waiting for
+    /// the others.
+    fn poll(&mut self) {
+        while let Some(measurement) = self.pending.front() {
+            let Some(disjoint) = Self::get_data::<
+                D3D10_QUERY_DATA_TIMESTAMP_DISJOINT,
+            >(&measurement.disjoint) else {
+                break;
+            };
+            let (Some(begin), Some(end)) = (
+                Self::get_data::<u64>(&measurement.begin),
+                Self::get_data::<u64>(&measurement.end),
+            ) else {
+                break;
+            };
+            // the timestamps are meaningless if the clock of the GPU changed
+            // in between, e.g. because of power management.
+            if !disjoint.Disjoint.as_bool() && disjoint.Frequency != 0 {
+                let ticks = end.saturating_sub(begin) as u128;
+                self.last = Some(Duration::from_nanos(
+                    (ticks * 1_000_000_000 / disjoint.Frequency as u128) as u64,
+                ));
+            }
+            let measurement = self.pending.pop_front().unwrap();
+            self.free.push(measurement);
+        }
+    }
+
+    /// Get the data of `query` if the GPU is done with it. `GetData` of the
+    /// bindings reports `S_FALSE`, i.e. not done yet, as a success, so the
+    /// result is checked on the raw call.
+    fn get_data<T>(query: &ID3D10Query) -> Option<T> {
+        let asynchronous: &ID3D10Asynchronous = query;
+        let mut data = mem::MaybeUninit::<T>::uninit();
+        let result = unsafe {
+            (Interface::vtable(asynchronous).GetData)(
+                Interface::as_raw(asynchronous),
+                data.as_mut_ptr() as _,
+                mem::size_of::<T>() as _,
+                D3D10_ASYNC_GETDATA_DONOTFLUSH.0 as _,
+            )
+        };
+        (result == S_OK).then(|| unsafe { data.assume_init() })
+    }
+
+    fn create_query(
+        device: &ID3D10Device,
+        query: D3D10_QUERY,
+    ) -> Result<ID3D10Query> {
+        let mut result = None;
+        unsafe {
+            device.CreateQuery(
+                &D3D10_QUERY_DESC {
+                    Query: query,
+                    MiscFlags: 0,
+                },
+                Some(&mut result),
+            )
+        }
+        .map_err(Error::create("timestamp query"))?;
+        Ok(result.unwrap())
+    }
+}
@@ -24,41 +24,270 @@
 //! with Direct3D10 and `egui`. This example uses `winit` for window management
 //! and event handling, while native Win32 APIs should also work well.
 
+/// Report a warning through [`Diagnostics`].
+macro_rules! warn {
+    ($diagnostics:expr, $($arg:tt)+) => {
+        $diagnostics.warn(format_args!($($arg)+))
+    };
+}
+
 mod texture;
 use texture::TexturePool;
+pub use texture::{TextureMemoryUsage, TextureUpdateStats};
+
+mod error;
+pub use error::{Error, Result};
+
+mod geometry_cache;
+use geometry_cache::GeometryCache;
+
+mod gpu_timer;
+use gpu_timer::GpuTimer;
+
+mod state_block;
+pub use state_block::{StateBlock, StateGuard};
+
+#[cfg(feature = "win32")]
+pub mod win32;
 
-use std::mem;
+use std::{marker::PhantomData, mem, rc::Rc};
 
 const fn zeroed<T>() -> T {
     unsafe { mem::zeroed() }
 }
 
+/// Where warnings go: to the handler set with
+/// [`Renderer::set_diagnostic_handler`], or to `log` if there is none and the
+/// `logging` feature is enabled.
+#[derive(Clone, Default)]
+struct Diagnostics(Option<Rc<DiagnosticHandler>>);
+
+/// A function receiving the warnings of the [`Renderer`]. See
+/// [`Renderer::set_diagnostic_handler`].
+pub type DiagnosticHandler = dyn Fn(&str);
+
+impl Diagnostics {
+    fn warn(&self, args: std::fmt::Arguments) {
+        match &self.0 {
+            Some(handler) => handler(&args.to_string()),
+            #[cfg(feature = "logging")]
+            None => log::warn!("{args}"),
+            #[cfg(not(feature = "logging"))]
+            None => {},
+        }
+    }
+}
+
+fn set_debug_name(child: &ID3D10DeviceChild, name: &str) -> Result<()> {
+    unsafe {
+        child.SetPrivateData(
+            &WKPDID_D3DDebugObjectName,
+            name.len() as _,
+            Some(name.as_ptr() as _),
+        )
+    }?;
+    Ok(())
+}
+
 use egui::{
     epaint::{textures::TexturesDelta, ClippedShape, Primitive, Vertex},
-    ClippedPrimitive, Pos2, Rgba,
+    ClippedPrimitive, Color32, Pos2,
 };
 
 use windows::{
-    core::{Interface, Result},
+    core::Interface,
     Win32::{
-        Foundation::{BOOL, RECT},
-        Graphics::{Direct3D::*, Direct3D10::*, Dxgi::Common::*},
+        Foundation::{BOOL, HANDLE, RECT},
+        Graphics::{
+            Direct3D::*,
+            Direct3D10::*,
+            Dxgi::{Common::*, IDXGISwapChain, DXGI_PRESENT},
+        },
     },
 };
 
 /// The core of this crate. You can set up a renderer via [`Renderer::new`]
 /// and render the output from `egui` with [`Renderer::render`].
+///
+/// ## Multiple Viewports
+///
+/// With multiple viewports, every viewport is a separate window with its own
+/// swap chain, and every [`egui::FullOutput`] already holds the shapes of a
+/// single viewport only: the one the pass of `egui` producing it ran for. So
+/// there is no need to pick the shapes of a viewport; render each output into
+/// the render target of the window of its viewport, with the scale factor of
+/// that window.
+///
+/// Use a single [`Renderer`] for all viewports sharing a device. Textures
+/// belong to the [`egui::Context`] rather than to a viewport, so a texture
+/// uploaded with the output of one viewport may be sampled by the others,
+/// and separate renderers would each miss the uploads of the others. A
+/// renderer keeps no state tied to a render target between calls, so it can
+/// render to any number of swap chains in any order.
+///
+/// ## Threading
+///
+/// A [`Renderer`] is neither [`Send`] nor [`Sync`], so the compiler rejects
+/// moving or sharing it across threads. Every call of the renderer issues a
+/// sequence of state changes on the device that must not interleave with
+/// those of another thread, so create the renderer on the thread that
+/// renders with the device and keep it there. Tessellation does not need the
+/// renderer and can run on a worker thread, see
+/// [`Renderer::render_primitives`].
 pub struct Renderer {
     device: ID3D10Device,
 
     input_layout: ID3D10InputLayout,
     vertex_shader: ID3D10VertexShader,
-    pixel_shader: ID3D10PixelShader,
+    transform_buffer: ID3D10Buffer,
+    linear_pixel_shader: ID3D10PixelShader,
+    gamma_pixel_shader: ID3D10PixelShader,
     rasterizer_state: ID3D10RasterizerState,
     sampler_state: ID3D10SamplerState,
     blend_state: ID3D10BlendState,
+    gamma_output: bool,
+    hdr_white_level: Option<f32>,
+    mip_lod_bias: f32,
+    sampler_filter: SamplerFilter,
+    address_mode: AddressMode,
+    border_color: egui::Rgba,
+    blend_mode: BlendMode,
+    alpha_to_coverage: bool,
+    sample_mask: u32,
+    sample_count: u32,
+    custom_rasterizer_desc: Option<D3D10_RASTERIZER_DESC>,
+    tint: egui::Rgba,
+    clear_color: Option<[f32; 4]>,
+    pixel_snapping: bool,
+    restore_state_after_render: bool,
+    debug_names: bool,
+    debug_unsupported_callbacks: bool,
+    vs_blob: Option<&'static [u8]>,
+    ps_blob: Option<&'static [u8]>,
+    warned_render_target_format: bool,
+    diagnostics: Diagnostics,
+
+    stats: RenderStats,
+    vertex_buffer: Option<ID3D10Buffer>,
+    index_buffer: Option<ID3D10Buffer>,
+    // `None` when the geometry cache is disabled.
+    geometry_cache: Option<GeometryCache>,
+    // `None` when GPU timing is disabled.
+    gpu_timer: Option<GpuTimer>,
 
     texture_pool: TexturePool,
+
+    // keeps the renderer `!Send` and `!Sync`, see the docs above.
+    _thread_bound: PhantomData<*const ()>,
+}
+
+impl std::fmt::Debug for Renderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Renderer")
+            .field("gamma_output", &self.gamma_output)
+            .field("hdr_white_level", &self.hdr_white_level)
+            .field("blend_mode", &self.blend_mode)
+            .field("sampler_filter", &self.sampler_filter)
+            .field("address_mode", &self.address_mode)
+            .field("sample_count", &self.sample_count)
+            .field("pixel_snapping", &self.pixel_snapping)
+            .field("geometry_cache", &self.geometry_cache.is_some())
+            .field(
+                "restore_state_after_render",
+                &self.restore_state_after_render,
+            )
+            .field("texture_mirror", &self.texture_pool.mirror())
+            .field(
+                "texture_count",
+                &self.texture_pool.memory_usage().texture_count,
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// A mesh of the current frame that is going to be drawn, see
+/// `prepare_primitives`. Vertices and indices are written straight from the
+/// tessellated mesh into the mapped buffers, without copying them first.
+struct PreparedMesh<'a> {
+    mesh: &'a egui::Mesh,
+    /// The indices of `mesh` without an incomplete trailing triangle.
+    indices: &'a [u32],
+    /// The clip rect in physical pixels of the frame.
+    clip_rect: egui::Rect,
+}
+
+impl PreparedMesh<'_> {
+    fn vertices(&self) -> impl Iterator<Item = VertexData> + '_ {
+        self.mesh
+            .vertices
+            .iter()
+            .map(|&Vertex { pos, uv, color }| VertexData { pos, uv, color })
+    }
+
+    fn to_mesh_data(&self) -> MeshData {
+        MeshData {
+            vertices: self.vertices().collect(),
+            indices: self.indices.to_vec(),
+            texture_id: self.mesh.texture_id,
+            clip_rect: self.clip_rect,
+        }
+    }
+}
+
+/// How the output of the [`Renderer`] is combined with the existing content
+/// of the render target. Select it with [`Renderer::set_blend_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Blend the output over the render target using its alpha, replacing
+    /// the alpha channel of the render target. This is suitable for drawing
+    /// `egui` directly onto an opaque swap chain.
+    #[default]
+    AlphaBlend,
+    /// Keep both color and alpha premultiplied, blending them with
+    /// `ONE, INV_SRC_ALPHA`.
+    ///
+    /// Use this when rendering into an intermediate texture that is later
+    /// copied (for example with `CopyResource`) rather than blended onto the
+    /// final target. Clear the intermediate texture to `[0.0; 4]` first: areas
+    /// not covered by `egui` then stay exactly zero, and covered areas hold
+    /// premultiplied color together with their coverage in alpha, so no
+    /// further blending is needed downstream.
+    PremultipliedAlpha,
+    /// Add the premultiplied output to the render target with `ONE, ONE`,
+    /// leaving the alpha channel of the render target untouched.
+    ///
+    /// This suits glows and other light effects drawn over a scene, but
+    /// makes ordinary widgets look washed out over bright backgrounds.
+    Additive,
+}
+
+/// How textures are filtered when they are sampled. Select it with
+/// [`Renderer::set_sampler_filter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplerFilter {
+    /// Interpolate linearly between texels and mip levels.
+    #[default]
+    Linear,
+    /// Pick the nearest texel and mip level, which keeps pixel art and crisp
+    /// icons from being blurred.
+    Nearest,
+    /// Filter anisotropically with the given maximum anisotropy, clamped to
+    /// `1..=16`. This keeps images scaled far down from shimmering, but only
+    /// together with mipmaps.
+    Anisotropic(u32),
+}
+
+/// How texture coordinates outside of `0.0..=1.0` are resolved when sampling.
+/// Select it with [`Renderer::set_address_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Sample the border color, see [`Renderer::set_border_color`].
+    #[default]
+    Border,
+    /// Repeat the texel at the nearest edge.
+    Clamp,
+    /// Tile the texture.
+    Wrap,
 }
 
 /// Part of [`egui::FullOutput`] that is consumed by [`Renderer::render`].
@@ -77,38 +306,320 @@ pub struct RendererOutput {
     pub pixels_per_point: f32,
 }
 
+impl std::fmt::Debug for RendererOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RendererOutput")
+            .field("shapes", &self.shapes.len())
+            .field("textures_delta", &!self.textures_delta.is_empty())
+            .field("pixels_per_point", &self.pixels_per_point)
+            .finish()
+    }
+}
+
+/// A [`egui::FullOutput`] split by [`split_output`] into the part consumed by
+/// the [`Renderer`] and the parts meant for the platform integration.
+pub struct SplitOutput {
+    /// The part consumed by [`Renderer::render`].
+    pub renderer: RendererOutput,
+    /// [`egui::FullOutput::platform_output`].
+    pub platform: egui::PlatformOutput,
+    /// [`egui::FullOutput::viewport_output`].
+    pub viewports: egui::ViewportIdMap<egui::ViewportOutput>,
+}
+
 /// Convenience method to split a [`egui::FullOutput`] into the
 /// [`RendererOutput`] part and other parts for platform integration.
-pub fn split_output(
-    full_output: egui::FullOutput,
-) -> (
-    RendererOutput,
-    egui::PlatformOutput,
-    egui::ViewportIdMap<egui::ViewportOutput>,
-) {
-    (
-        RendererOutput {
+pub fn split_output(full_output: egui::FullOutput) -> SplitOutput {
+    SplitOutput {
+        renderer: RendererOutput {
             textures_delta: full_output.textures_delta,
             shapes: full_output.shapes,
             pixels_per_point: full_output.pixels_per_point,
         },
-        full_output.platform_output,
-        full_output.viewport_output,
-    )
+        platform: full_output.platform_output,
+        viewports: full_output.viewport_output,
+    }
+}
+
+/// Create a Direct3D10 device on WARP, the software rasterizer that ships
+/// with Windows.
+///
+/// This is meant for tests and headless CI machines without a GPU, where it
+/// allows to create a [`Renderer`] and render into an offscreen target, see
+/// [`Renderer::create_offscreen_target`]. WARP is far slower than any GPU,
+/// so do not use it for anything else.
+pub fn create_warp_device() -> Result<ID3D10Device> {
+    let mut device = None;
+    unsafe {
+        D3D10CreateDevice(
+            None,
+            D3D10_DRIVER_TYPE_WARP,
+            None,
+            0,
+            D3D10_SDK_VERSION,
+            Some(&mut device),
+        )
+    }
+    .map_err(Error::create("WARP device"))?;
+    Ok(device.unwrap())
+}
+
+/// Get the width and height in pixels of the texture behind a render target
+/// view, i.e. the size [`Renderer::render`] renders into, e.g. to lay out
+/// the UI, or to compute a viewport for [`Renderer::render_to_viewport`].
+pub fn render_target_size(rtv: &ID3D10RenderTargetView) -> Result<(u32, u32)> {
+    let tex = Renderer::get_render_target_texture(rtv)?;
+    let mut desc = zeroed();
+    unsafe { tex.GetDesc(&mut desc) };
+    Ok((desc.Width, desc.Height))
 }
 
+/// A single vertex of a [`MeshData`], laid out exactly as it is uploaded to
+/// the vertex buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(C)]
-struct VertexData {
-    pos: Pos2,
-    uv: Pos2,
-    color: Rgba,
+pub struct VertexData {
+    /// Position in points, unchanged from `egui`. The vertex shader
+    /// transforms it into normalized device coordinates of the render target.
+    pub pos: Pos2,
+    /// Texture coordinates, unchanged from `egui`.
+    pub uv: Pos2,
+    /// Premultiplied vertex color in gamma space, unchanged from `egui`. The
+    /// vertex shader converts it to linear space.
+    pub color: Color32,
 }
 
-struct MeshData {
-    vtx: Vec<VertexData>,
-    idx: Vec<u32>,
-    tex: egui::TextureId,
-    clip_rect: egui::Rect,
+/// The number of render targets [`Renderer::render_to_targets`] accepts at
+/// most, which is also the number of outputs of the pixel shaders.
+const MAX_RENDER_TARGETS: usize =
+    D3D10_SIMULTANEOUS_RENDER_TARGET_COUNT as usize;
+
+/// The region of the render target that egui is rendered into, in physical
+/// pixels. Positions and clip rects of egui are relative to its top-left
+/// corner.
+#[derive(Clone, Copy)]
+struct Frame {
+    offset: (i32, i32),
+    size: (u32, u32),
+}
+
+/// Content of the constant buffer of the vertex shader, see `egui.hlsl`.
+#[repr(C)]
+struct Transform {
+    /// Scale in `xy` and offset in `zw` from physical pixels to normalized
+    /// device coordinates.
+    ndc_from_pixels: [f32; 4],
+    pixels_per_point: f32,
+    /// `1.0` to round positions to whole pixels, `0.0` otherwise.
+    snap_to_pixels: f32,
+    _padding: [f32; 2],
+    /// Multiplied into every vertex color, in linear space.
+    tint: [f32; 4],
+}
+
+/// A tessellated mesh as it is drawn by the [`Renderer`].
+///
+/// This is what [`Renderer::render_with_draw_list`] returns for inspection.
+/// The fields reflect the vertex format and coordinate transform the renderer
+/// currently uses internally, so they are not considered stable and may
+/// change between minor versions of this crate.
+#[derive(Clone, Debug)]
+pub struct MeshData {
+    /// Vertices of the mesh in points, as uploaded.
+    pub vertices: Vec<VertexData>,
+    /// Triangle list indices into [`MeshData::vertices`].
+    pub indices: Vec<u32>,
+    /// The texture sampled by this mesh.
+    pub texture_id: egui::TextureId,
+    /// The scissor rectangle in physical pixels of the render target.
+    pub clip_rect: egui::Rect,
+}
+
+/// Settings of a [`Renderer`], passed to [`Renderer::with_config`].
+///
+/// Every option can also be changed later through the corresponding setter
+/// of the [`Renderer`], whose documentation describes it in detail.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RendererConfig {
+    /// Whether the output is gamma-encoded by the pixel shader, for render
+    /// targets that do not do so by themselves. Defaults to `false`. See
+    /// [`Renderer::set_gamma_output`].
+    pub gamma_output: bool,
+    /// Defaults to `None`. See [`Renderer::set_hdr_white_level`].
+    pub hdr_white_level: Option<f32>,
+    /// Whether the pipeline state is restored after rendering. Defaults to
+    /// `false`. See [`Renderer::set_restore_state_after_render`].
+    pub restore_state_after_render: bool,
+    /// Defaults to [`SamplerFilter::Linear`]. See
+    /// [`Renderer::set_sampler_filter`].
+    pub sampler_filter: SamplerFilter,
+    /// Defaults to [`AddressMode::Border`]. See
+    /// [`Renderer::set_address_mode`].
+    pub address_mode: AddressMode,
+    /// Defaults to [`egui::Rgba::WHITE`]. See [`Renderer::set_border_color`].
+    pub border_color: egui::Rgba,
+    /// Defaults to `0.0`. See [`Renderer::set_mip_lod_bias`].
+    pub mip_lod_bias: f32,
+    /// Defaults to [`BlendMode::AlphaBlend`]. See
+    /// [`Renderer::set_blend_mode`].
+    pub blend_mode: BlendMode,
+    /// Defaults to `false`. See [`Renderer::set_alpha_to_coverage`].
+    pub alpha_to_coverage: bool,
+    /// Defaults to `u32::MAX`. See [`Renderer::set_sample_mask`].
+    pub sample_mask: u32,
+    /// Defaults to `1`. See [`Renderer::set_sample_count`].
+    pub sample_count: u32,
+    /// Defaults to `None`. See [`Renderer::set_custom_rasterizer_desc`].
+    pub custom_rasterizer_desc: Option<D3D10_RASTERIZER_DESC>,
+    /// Defaults to [`egui::Rgba::WHITE`]. See [`Renderer::set_tint`].
+    pub tint: egui::Rgba,
+    /// Defaults to `None`. See [`Renderer::set_clear_color`].
+    pub clear_color: Option<[f32; 4]>,
+    /// Defaults to `false`. See [`Renderer::set_pixel_snapping`].
+    pub pixel_snapping: bool,
+    /// Defaults to `false`. See [`Renderer::set_geometry_cache`].
+    pub geometry_cache: bool,
+    /// Defaults to `false`. See [`Renderer::set_gpu_timing`].
+    pub gpu_timing: bool,
+    /// Defaults to `true`. See [`Renderer::set_texture_mirror`].
+    pub texture_mirror: bool,
+    /// Defaults to `true`. See [`Renderer::set_srgb_textures`].
+    pub srgb_textures: bool,
+    /// Defaults to `false`. See [`Renderer::set_texture_mipmaps`].
+    pub texture_mipmaps: bool,
+    /// Defaults to `false`. See [`Renderer::set_premultiply_textures`].
+    pub premultiply_textures: bool,
+    /// Defaults to `None`. See [`Renderer::set_texture_budget`].
+    pub texture_budget: Option<usize>,
+    /// Defaults to `false`. See [`Renderer::set_debug_names`].
+    pub debug_names: bool,
+    /// Defaults to `false`. See
+    /// [`Renderer::set_debug_unsupported_callbacks`].
+    pub debug_unsupported_callbacks: bool,
+    /// A compiled vertex shader to use instead of the built-in one. Defaults
+    /// to `None`.
+    ///
+    /// It has to keep the contract of `vs_main` in `shaders/egui.hlsl`: the
+    /// same `POSITION`, `TEXCOORD` and `COLOR` inputs, described by
+    /// [`VertexData`], and the same `Transform` constant buffer in `b0`. Its
+    /// outputs have to match the pixel shader.
+    pub vs_blob: Option<&'static [u8]>,
+    /// A compiled pixel shader to use instead of the built-in ones. Defaults
+    /// to `None`.
+    ///
+    /// It samples the texture of each mesh from `t0` with the sampler in
+    /// `s0`, like `ps_main_linear` in `shaders/egui.hlsl`. It replaces both
+    /// built-in pixel shaders, so [`Renderer::set_gamma_output`] has no
+    /// effect with it.
+    pub ps_blob: Option<&'static [u8]>,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            gamma_output: false,
+            hdr_white_level: None,
+            restore_state_after_render: false,
+            sampler_filter: SamplerFilter::default(),
+            address_mode: AddressMode::default(),
+            border_color: egui::Rgba::WHITE,
+            mip_lod_bias: 0.,
+            blend_mode: BlendMode::default(),
+            alpha_to_coverage: false,
+            sample_mask: u32::MAX,
+            sample_count: 1,
+            custom_rasterizer_desc: None,
+            tint: egui::Rgba::WHITE,
+            clear_color: None,
+            pixel_snapping: false,
+            geometry_cache: false,
+            gpu_timing: false,
+            texture_mirror: true,
+            srgb_textures: true,
+            texture_mipmaps: false,
+            premultiply_textures: false,
+            texture_budget: None,
+            debug_names: false,
+            debug_unsupported_callbacks: false,
+            vs_blob: None,
+            ps_blob: None,
+        }
+    }
+}
+
+/// A paint callback for the [`Renderer`], run in the middle of rendering to
+/// issue your own draw calls, e.g. to embed a 3D viewport into the UI.
+///
+/// Put it into the [`egui::PaintCallback::callback`] of a
+/// [`egui::PaintCallback`] and add it to the UI with
+/// [`egui::Painter::add`]. Callbacks of any other type are skipped with a
+/// warning.
+///
+/// When the callback runs, the pipeline state described in
+/// [`Renderer::render`] is bound, the viewport covers
+/// [`egui::PaintCallbackInfo::viewport`] and the scissor rect covers
+/// [`egui::PaintCallbackInfo::clip_rect`]. The callback may change any state;
+/// the renderer binds its own state again before drawing the next mesh.
+pub struct PaintCallbackFn {
+    f: Box<PaintCallbackFnInner>,
+}
+
+type PaintCallbackFnInner =
+    dyn Fn(egui::PaintCallbackInfo, &ID3D10Device) + Send + Sync;
+
+impl PaintCallbackFn {
+    /// Wrap a closure that receives the [`egui::PaintCallbackInfo`] and the
+    /// device (context) the output is rendered with.
+    pub fn new(
+        callback: impl Fn(egui::PaintCallbackInfo, &ID3D10Device)
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            f: Box::new(callback),
+        }
+    }
+}
+
+/// The cost of rendering a [`RendererOutput`], as measured by
+/// [`Renderer::estimate_cost`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// Number of meshes, each of which is a separate draw call.
+    pub mesh_count: usize,
+    /// Total number of vertices over all meshes.
+    pub vertex_count: usize,
+    /// Total number of indices over all meshes.
+    pub index_count: usize,
+    /// Number of bytes of texture data uploaded to the GPU.
+    pub texture_upload_bytes: usize,
+}
+
+/// What the [`Renderer`] drew in a frame, as reported by
+/// [`Renderer::last_render_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Number of meshes drawn. Each mesh is drawn with a single draw call.
+    pub mesh_count: usize,
+    /// Total number of vertices of the meshes drawn.
+    pub vertex_count: usize,
+    /// Total number of indices of the meshes drawn.
+    pub index_count: usize,
+    /// Number of paint callbacks run.
+    pub callback_count: usize,
+    /// The textures created, updated and freed by the texture delta of the
+    /// frame. Unlike the other counts, these are reported even if nothing
+    /// was drawn.
+    pub textures: TextureUpdateStats,
+}
+
+impl RenderStats {
+    fn count_mesh(&mut self, mesh: &PreparedMesh) {
+        self.mesh_count += 1;
+        self.vertex_count += mesh.mesh.vertices.len();
+        self.index_count += mesh.indices.len();
+    }
 }
 
 impl Renderer {
@@ -116,238 +627,2328 @@ impl Renderer {
     /// [`Renderer`] holds various Direct3D10 resources and states derived
     /// from the device.
     ///
+    /// This is a shorthand for [`Renderer::with_config`] with every option
+    /// but [`RendererConfig::gamma_output`] left at its default.
+    ///
+    /// An [`ID3D10Device1`] can be passed as is, since it dereferences to
+    /// [`ID3D10Device`]. Its feature level has to be
+    /// `D3D10_FEATURE_LEVEL_10_0` or `D3D10_FEATURE_LEVEL_10_1`, as the
+    /// shaders of the renderer are not available for the 9_x feature levels.
+    ///
     /// If any Direct3D resource creation fails, this function will return an
     /// error. You can create the Direct3D10 device with debug layer enabled
     /// to find out details on the error.
     pub fn new(device: &ID3D10Device, gamma_output: bool) -> Result<Self> {
+        Self::with_config(
+            device,
+            RendererConfig {
+                gamma_output,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Create a [`Renderer`] using the provided Direct3D10 device and
+    /// [`RendererConfig`]. See [`Renderer::new`] for the supported devices.
+    ///
+    /// If any Direct3D resource creation fails, this function will return an
+    /// error. You can create the Direct3D10 device with debug layer enabled
+    /// to find out details on the error.
+    pub fn with_config(
+        device: &ID3D10Device,
+        config: RendererConfig,
+    ) -> Result<Self> {
         let mut input_layout = None;
         let mut vertex_shader = None;
-        let mut pixel_shader = None;
+        let mut transform_buffer = None;
+        let mut linear_pixel_shader = None;
+        let mut gamma_pixel_shader = None;
         let mut rasterizer_state = None;
         let mut sampler_state = None;
         let mut blend_state = None;
+        if let Ok(device1) = device.cast::<ID3D10Device1>() {
+            let feature_level = unsafe { device1.GetFeatureLevel() };
+            if feature_level.0 < D3D10_FEATURE_LEVEL_10_0.0 {
+                return Err(Error::InvalidArgument(format!(
+                    "unsupported feature level {:#x}",
+                    feature_level.0
+                )));
+            }
+        }
+        let vs_blob = config.vs_blob.unwrap_or(Self::VS_BLOB);
         unsafe {
-            device.CreateInputLayout(
-                &Self::INPUT_ELEMENTS_DESC,
-                Self::VS_BLOB,
-                Some(&mut input_layout),
-            )?;
             device
-                .CreateVertexShader(Self::VS_BLOB, Some(&mut vertex_shader))?;
-            device.CreatePixelShader(
-                if gamma_output {
-                    Self::PS_GAMMA_BLOB
-                } else {
-                    Self::PS_LINEAR_BLOB
-                },
-                Some(&mut pixel_shader),
-            )?;
-            device.CreateRasterizerState(
-                &Self::RASTERIZER_DESC,
-                Some(&mut rasterizer_state),
-            )?;
-            device.CreateSamplerState(
-                &Self::SAMPLER_DESC,
-                Some(&mut sampler_state),
-            )?;
+                .CreateInputLayout(
+                    &Self::INPUT_ELEMENTS_DESC,
+                    vs_blob,
+                    Some(&mut input_layout),
+                )
+                .map_err(Error::create("input layout"))?;
+            device
+                .CreateVertexShader(vs_blob, Some(&mut vertex_shader))
+                .map_err(Error::create("vertex shader"))?;
+            device
+                .CreateBuffer(
+                    &D3D10_BUFFER_DESC {
+                        ByteWidth: mem::size_of::<Transform>() as _,
+                        Usage: D3D10_USAGE_DYNAMIC,
+                        BindFlags: D3D10_BIND_CONSTANT_BUFFER.0 as _,
+                        CPUAccessFlags: D3D10_CPU_ACCESS_WRITE.0 as _,
+                        ..D3D10_BUFFER_DESC::default()
+                    },
+                    None,
+                    Some(&mut transform_buffer),
+                )
+                .map_err(Error::create("constant buffer"))?;
+            device
+                .CreatePixelShader(
+                    config.ps_blob.unwrap_or(Self::PS_LINEAR_BLOB),
+                    Some(&mut linear_pixel_shader),
+                )
+                .map_err(Error::create("linear pixel shader"))?;
+            device
+                .CreatePixelShader(
+                    config.ps_blob.unwrap_or(Self::PS_GAMMA_BLOB),
+                    Some(&mut gamma_pixel_shader),
+                )
+                .map_err(Error::create("gamma pixel shader"))?;
             device
-                .CreateBlendState(&Self::BLEND_DESC, Some(&mut blend_state))?;
+                .CreateRasterizerState(
+                    &Self::rasterizer_desc(
+                        config.sample_count,
+                        config.custom_rasterizer_desc,
+                    ),
+                    Some(&mut rasterizer_state),
+                )
+                .map_err(Error::create("rasterizer state"))?;
+            device
+                .CreateSamplerState(
+                    &Self::sampler_desc(
+                        config.mip_lod_bias,
+                        config.sampler_filter,
+                        config.address_mode,
+                        config.border_color,
+                    ),
+                    Some(&mut sampler_state),
+                )
+                .map_err(Error::create("sampler state"))?;
+            device
+                .CreateBlendState(
+                    &Self::blend_desc(
+                        config.blend_mode,
+                        config.alpha_to_coverage,
+                    ),
+                    Some(&mut blend_state),
+                )
+                .map_err(Error::create("blend state"))?;
         };
-        Ok(Self {
+        let mut renderer = Self {
             device: device.clone(),
             input_layout: input_layout.unwrap(),
             vertex_shader: vertex_shader.unwrap(),
-            pixel_shader: pixel_shader.unwrap(),
+            transform_buffer: transform_buffer.unwrap(),
+            linear_pixel_shader: linear_pixel_shader.unwrap(),
+            gamma_pixel_shader: gamma_pixel_shader.unwrap(),
             rasterizer_state: rasterizer_state.unwrap(),
             sampler_state: sampler_state.unwrap(),
             blend_state: blend_state.unwrap(),
+            gamma_output: config.gamma_output,
+            hdr_white_level: None,
+            mip_lod_bias: config.mip_lod_bias,
+            sampler_filter: config.sampler_filter,
+            address_mode: config.address_mode,
+            border_color: config.border_color,
+            blend_mode: config.blend_mode,
+            alpha_to_coverage: config.alpha_to_coverage,
+            sample_mask: config.sample_mask,
+            sample_count: config.sample_count,
+            custom_rasterizer_desc: config.custom_rasterizer_desc,
+            tint: config.tint,
+            clear_color: config.clear_color,
+            pixel_snapping: config.pixel_snapping,
+            restore_state_after_render: config.restore_state_after_render,
+            debug_names: false,
+            debug_unsupported_callbacks: config.debug_unsupported_callbacks,
+            vs_blob: config.vs_blob,
+            ps_blob: config.ps_blob,
+            warned_render_target_format: false,
+            diagnostics: Diagnostics::default(),
+            stats: RenderStats::default(),
+            vertex_buffer: None,
+            index_buffer: None,
+            geometry_cache: config.geometry_cache.then(GeometryCache::default),
+            gpu_timer: config.gpu_timing.then(GpuTimer::default),
             texture_pool: TexturePool::new(device),
-        })
+            _thread_bound: PhantomData,
+        };
+        renderer.texture_pool.set_mirror(config.texture_mirror)?;
+        renderer.texture_pool.set_srgb(config.srgb_textures)?;
+        renderer.texture_pool.set_mipmaps(config.texture_mipmaps);
+        renderer
+            .texture_pool
+            .set_premultiply(config.premultiply_textures);
+        renderer.texture_pool.set_budget(config.texture_budget);
+        renderer.set_debug_names(config.debug_names)?;
+        renderer.set_hdr_white_level(config.hdr_white_level)?;
+        Ok(renderer)
+    }
+
+    /// Get the current settings of the renderer as a [`RendererConfig`],
+    /// e.g. to create another renderer with the same settings.
+    pub fn config(&self) -> RendererConfig {
+        RendererConfig {
+            gamma_output: self.gamma_output,
+            hdr_white_level: self.hdr_white_level,
+            restore_state_after_render: self.restore_state_after_render,
+            sampler_filter: self.sampler_filter,
+            address_mode: self.address_mode,
+            border_color: self.border_color,
+            mip_lod_bias: self.mip_lod_bias,
+            blend_mode: self.blend_mode,
+            alpha_to_coverage: self.alpha_to_coverage,
+            sample_mask: self.sample_mask,
+            sample_count: self.sample_count,
+            custom_rasterizer_desc: self.custom_rasterizer_desc,
+            tint: self.tint,
+            clear_color: self.clear_color,
+            pixel_snapping: self.pixel_snapping,
+            geometry_cache: self.geometry_cache.is_some(),
+            gpu_timing: self.gpu_timer.is_some(),
+            texture_mirror: self.texture_pool.mirror(),
+            srgb_textures: self.texture_pool.srgb(),
+            texture_mipmaps: self.texture_pool.mipmaps(),
+            premultiply_textures: self.texture_pool.premultiply(),
+            texture_budget: self.texture_pool.budget(),
+            debug_names: self.debug_names,
+            debug_unsupported_callbacks: self.debug_unsupported_callbacks,
+            vs_blob: self.vs_blob,
+            ps_blob: self.ps_blob,
+        }
+    }
+
+    /// Get the Direct3D10 device the renderer creates its resources on, i.e.
+    /// the one passed to [`Renderer::new`] or to the latest
+    /// [`Renderer::reset_device`].
+    pub fn device(&self) -> &ID3D10Device {
+        &self.device
+    }
+
+    /// Move the renderer to a new Direct3D10 device, e.g. after the previous
+    /// one has been removed or reset.
+    ///
+    /// All shaders and states are recreated on `device` with the current
+    /// settings of the renderer. Every texture uploaded by `egui` is recreated
+    /// from the CPU mirror of its pixels kept by the renderer, so `egui` does
+    /// not need to send them again and they survive the reset automatically.
+    /// If the mirror has been disabled with [`Renderer::set_texture_mirror`],
+    /// the affected textures are dropped instead.
+    ///
+    /// Textures registered with [`Renderer::register_native_texture`] or
+    /// [`Renderer::register_shared_texture`] belong to the previous device,
+    /// and the renderer cannot recreate them by itself. Their ids are
+    /// returned and stay reserved: recreate each texture on the new device
+    /// and supply a view of it with [`Renderer::update_native_texture`], so
+    /// that the UI can keep referring to the same [`egui::TextureId`]. Until
+    /// then, meshes sampling it are drawn as if the texture did not exist.
+    /// Free the ones no longer needed with [`Renderer::free_user_texture`].
+    /// Sampler filters set with [`Renderer::set_texture_sampler_filter`] and
+    /// keyed mutexes of shared textures do not carry over.
+    ///
+    /// If this function fails, the renderer is left untouched and still refers
+    /// to the previous device.
+    pub fn reset_device(
+        &mut self,
+        device: &ID3D10Device,
+    ) -> Result<Vec<egui::TextureId>> {
+        let mut renderer = Self::with_config(device, self.config())?;
+        let lost = self.texture_pool.rebuild_on_device(device)?;
+        renderer.texture_pool =
+            mem::replace(&mut self.texture_pool, TexturePool::new(device));
+        renderer.diagnostics = mem::take(&mut self.diagnostics);
+        *self = renderer;
+        Ok(lost)
+    }
+
+    /// Register a shader resource view created by yourself, so that `egui` can
+    /// sample it through the returned [`egui::TextureId`], e.g. with
+    /// [`egui::Image`].
+    ///
+    /// Registered textures are never touched by the texture updates and
+    /// frees coming from `egui`; they stay until you call
+    /// [`Renderer::free_user_texture`].
+    ///
+    /// This also makes it possible to feed the output of the renderer back
+    /// into `egui`: render into an offscreen texture, post-process it (e.g.
+    /// blur it for a "frosted glass" effect) and register a view of the
+    /// result to sample it in a subsequent pass. A texture must not be
+    /// sampled by the pass that renders into it; meshes doing so are skipped
+    /// with a warning. See the [`egui-feedback`](https://github.com/philpax/egui-directx10/blob/main/examples/egui-feedback.rs)
+    /// example.
+    pub fn register_native_texture(
+        &mut self,
+        srv: ID3D10ShaderResourceView,
+    ) -> egui::TextureId {
+        self.texture_pool.register_native(srv, None)
+    }
+
+    /// Open a texture shared by another device, e.g. of another process, from
+    /// its shared `handle` and register it as with
+    /// [`Renderer::register_native_texture`].
+    ///
+    /// If the texture was created with
+    /// `D3D10_RESOURCE_MISC_SHARED_KEYEDMUTEX` (or its Direct3D11
+    /// equivalent), the renderer acquires its `IDXGIKeyedMutex` with key `0`
+    /// before issuing the draw calls of a frame that samples it, and releases
+    /// it with key `0` right after them. Acquiring blocks until the other
+    /// device releases the mutex, so it must release it with key `0` as well
+    /// once it is done writing. Without a keyed mutex, synchronizing access
+    /// to the texture is up to you.
+    ///
+    /// The format of the texture has to be one a shader resource view can be
+    /// created with, i.e. not a typeless one.
+    pub fn register_shared_texture(
+        &mut self,
+        handle: HANDLE,
+    ) -> Result<egui::TextureId> {
+        let mut texture: Option<ID3D10Texture2D> = None;
+        unsafe {
+            self.device.OpenSharedResource(
+                handle,
+                &ID3D10Texture2D::IID,
+                Some(&mut texture as *mut _ as *mut _),
+            )
+        }?;
+        let texture = texture.unwrap();
+        let mut srv = None;
+        unsafe {
+            self.device
+                .CreateShaderResourceView(&texture, None, Some(&mut srv))
+        }
+        .map_err(Error::create("shader resource view"))?;
+        Ok(self
+            .texture_pool
+            .register_native(srv.unwrap(), texture.cast().ok()))
+    }
+
+    /// Replace the shader resource view behind a texture registered with
+    /// [`Renderer::register_native_texture`], keeping its
+    /// [`egui::TextureId`]. This suits content that arrives in a new texture
+    /// every frame, like decoded video frames.
+    ///
+    /// This also supplies a texture lost by [`Renderer::reset_device`] again,
+    /// created on the new device.
+    ///
+    /// Returns `false`, without registering anything, if `tid` is not a
+    /// registered native texture.
+    pub fn update_native_texture(
+        &mut self,
+        tid: egui::TextureId,
+        srv: ID3D10ShaderResourceView,
+    ) -> bool {
+        self.texture_pool.update_native(tid, srv)
+    }
+
+    /// Free a texture registered with [`Renderer::register_native_texture`].
+    /// Ids of textures managed by `egui` are ignored.
+    pub fn free_user_texture(&mut self, tid: egui::TextureId) {
+        self.texture_pool.free_native(tid);
+    }
+
+    /// Drop all textures of the renderer, i.e. the ones uploaded by `egui`
+    /// along with the ones registered with
+    /// [`Renderer::register_native_texture`] or
+    /// [`Renderer::register_shared_texture`], without recreating the shaders
+    /// and states of the renderer.
+    ///
+    /// This is meant for switching to another [`egui::Context`], which starts
+    /// over with uploading the font atlas and its other textures. The
+    /// textures of the previous context must not be drawn afterwards, and
+    /// native textures still needed have to be registered again.
+    pub fn clear_textures(&mut self) {
+        self.texture_pool.clear();
+    }
+
+    /// Sample the texture registered as `tid` with `sampler_filter` instead
+    /// of the filter set with [`Renderer::set_sampler_filter`], e.g. to keep
+    /// pixel art crisp while the font atlas is still filtered linearly. Pass
+    /// `None` to go back to the filter of the renderer.
+    ///
+    /// This creates a sampler state for the texture, which follows the other
+    /// sampler settings of the renderer, e.g. [`Renderer::set_address_mode`].
+    /// It is dropped along with the texture. Fails with
+    /// [`Error::InvalidArgument`] if `tid` is not a texture registered with
+    /// [`Renderer::register_native_texture`] or
+    /// [`Renderer::register_shared_texture`].
+    pub fn set_texture_sampler_filter(
+        &mut self,
+        tid: egui::TextureId,
+        sampler_filter: Option<SamplerFilter>,
+    ) -> Result<()> {
+        let sampler = match sampler_filter {
+            Some(filter) => Some((
+                filter,
+                self.create_sampler_state(
+                    self.mip_lod_bias,
+                    filter,
+                    self.address_mode,
+                    self.border_color,
+                )?,
+            )),
+            None => None,
+        };
+        if !self.texture_pool.set_native_sampler(tid, sampler) {
+            return Err(Error::InvalidArgument(format!(
+                "{tid:?} is not a registered native texture"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Apply `textures_delta` right away, without rendering anything, just
+    /// like the render functions do before drawing.
+    ///
+    /// This allows uploading textures ahead of time, e.g. the font atlas
+    /// during a loading screen, so that the first rendered frame does not pay
+    /// for it. Take the delta out of the output of `egui` with
+    /// [`std::mem::take`] before rendering the rest of it, as every delta
+    /// must be applied exactly once.
+    ///
+    /// This is also what decouples texture management from drawing: the
+    /// render functions apply the delta they are given in the same way, so
+    /// passing them an empty delta afterwards draws with the textures
+    /// uploaded here.
+    ///
+    /// Returns how many textures were created, updated and freed.
+    pub fn upload_textures(
+        &mut self,
+        device_context: &ID3D10Device,
+        textures_delta: TexturesDelta,
+    ) -> Result<TextureUpdateStats> {
+        self.texture_pool.update(device_context, textures_delta)
+    }
+
+    /// Get the shader resource view the renderer samples for `tid`, whether
+    /// the texture was uploaded by `egui` (e.g. the font atlas) or registered
+    /// with [`Renderer::register_native_texture`].
+    ///
+    /// The view of a texture uploaded by `egui` is replaced whenever `egui`
+    /// sends the whole texture again, so do not hold on to it for longer than
+    /// a frame.
+    pub fn get_texture_srv(
+        &self,
+        tid: egui::TextureId,
+    ) -> Option<ID3D10ShaderResourceView> {
+        self.texture_pool.get_srv(tid)
+    }
+
+    /// Get the number of textures uploaded by `egui` and the memory they take.
+    /// Textures registered with [`Renderer::register_native_texture`] are not
+    /// counted.
+    pub fn texture_memory_usage(&self) -> TextureMemoryUsage {
+        self.texture_pool.memory_usage()
+    }
+
+    /// Check whether the renderer keeps a CPU mirror of texture pixels. See
+    /// [`Renderer::set_texture_mirror`].
+    pub fn texture_mirror(&self) -> bool {
+        self.texture_pool.mirror()
+    }
+
+    /// Keep a CPU mirror of the pixels of every texture uploaded by `egui`.
+    /// This is enabled by default.
+    ///
+    /// Disabling the mirror saves roughly as much memory as the textures take
+    /// on the GPU and drops the mirrors that already exist, recreating the
+    /// affected textures. The mirror is what allows textures to survive
+    /// [`Renderer::reset_device`]: textures without one are dropped on reset
+    /// and `egui` has to upload them again.
+    pub fn set_texture_mirror(&mut self, texture_mirror: bool) -> Result<()> {
+        self.texture_pool.set_mirror(texture_mirror)
+    }
+
+    /// Get the current tint. See [`Renderer::set_tint`].
+    pub fn tint(&self) -> egui::Rgba {
+        self.tint
+    }
+
+    /// Multiply everything the renderer draws by `tint`, e.g. to fade the UI
+    /// in and out or to dim it behind a modal dialog. It defaults to
+    /// [`egui::Rgba::WHITE`], which leaves the output unchanged.
+    ///
+    /// Like all colors in the renderer, `tint` is premultiplied, so use e.g.
+    /// [`egui::Rgba::from_white_alpha`] to change the opacity alone. Paint
+    /// callbacks are not affected.
+    pub fn set_tint(&mut self, tint: egui::Rgba) {
+        self.tint = tint;
+    }
+
+    /// Get the color render targets are cleared to before rendering, if any.
+    /// See [`Renderer::set_clear_color`].
+    pub fn clear_color(&self) -> Option<[f32; 4]> {
+        self.clear_color
+    }
+
+    /// Clear the render targets to `clear_color` with
+    /// `ClearRenderTargetView` before rendering, so that the caller does not
+    /// need to. It defaults to `None`, which leaves clearing to the caller.
+    ///
+    /// The color is written to the render target as is, without any gamma
+    /// encoding. The whole render target is cleared, even when rendering to
+    /// a viewport with [`Renderer::render_to_viewport`], and it is cleared
+    /// even if there is nothing to draw.
+    pub fn set_clear_color(&mut self, clear_color: Option<[f32; 4]>) {
+        self.clear_color = clear_color;
+    }
+
+    /// Check whether the output is gamma-encoded by the pixel shader. See
+    /// [`Renderer::set_gamma_output`].
+    pub fn gamma_output(&self) -> bool {
+        self.gamma_output
+    }
+
+    /// Gamma-encode the output in the pixel shader, for render targets that
+    /// do not do so by themselves, e.g. `DXGI_FORMAT_R8G8B8A8_UNORM`. Leave
+    /// it disabled for sRGB render targets like
+    /// `DXGI_FORMAT_R8G8B8A8_UNORM_SRGB`.
+    ///
+    /// The channel order of the render target does not matter: the output
+    /// merger writes the red, green and blue output of the pixel shader to
+    /// the matching channels of BGRA formats like
+    /// `DXGI_FORMAT_B8G8R8A8_UNORM_SRGB` as well.
+    ///
+    /// Both pixel shaders are created with the renderer, so this is cheap
+    /// enough to switch between render targets of either kind every frame.
+    pub fn set_gamma_output(&mut self, gamma_output: bool) {
+        self.gamma_output = gamma_output;
+    }
+
+    /// Get the brightness of white in HDR output, if enabled. See
+    /// [`Renderer::set_hdr_white_level`].
+    pub fn hdr_white_level(&self) -> Option<f32> {
+        self.hdr_white_level
+    }
+
+    /// Render for an scRGB render target, e.g. a
+    /// `DXGI_FORMAT_R16G16B16A16_FLOAT` swap chain of an HDR display, with
+    /// the white of `egui` at `white_level` nits. Pass `None`, the default,
+    /// to go back to SDR output.
+    ///
+    /// scRGB is linear with `1.0` at 80 nits, so the output is scaled by
+    /// `white_level / 80.0`; the SDR white level Windows reports for the
+    /// display, e.g. through `DISPLAYCONFIG_SDR_WHITE_LEVEL`, makes the UI
+    /// as bright as the rest of the desktop. HDR output is linear, so it
+    /// takes precedence over [`Renderer::set_gamma_output`]. Paint callbacks
+    /// are not affected. Fails if `white_level` is not positive.
+    pub fn set_hdr_white_level(
+        &mut self,
+        white_level: Option<f32>,
+    ) -> Result<()> {
+        if let Some(white_level) = white_level {
+            if !(white_level > 0.0 && white_level.is_finite()) {
+                return Err(Error::InvalidArgument(format!(
+                    "invalid HDR white level {white_level}"
+                )));
+            }
+        }
+        self.hdr_white_level = white_level;
+        Ok(())
+    }
+
+    /// Check whether color textures uploaded by `egui` get mipmaps. See
+    /// [`Renderer::set_texture_mipmaps`].
+    pub fn texture_mipmaps(&self) -> bool {
+        self.texture_pool.mipmaps()
+    }
+
+    /// Create color images uploaded by `egui` from now on with a full mip
+    /// chain, generated on the GPU with `GenerateMips`. This is off by
+    /// default.
+    ///
+    /// Mipmaps keep large images from aliasing when they are drawn at a small
+    /// size, e.g. as thumbnails, especially together with
+    /// [`SamplerFilter::Anisotropic`]. They take a third more GPU memory, and
+    /// partial updates of such images generate all mipmaps again. The font
+    /// atlas never gets mipmaps, as it is always drawn at its own size.
+    ///
+    /// Textures registered with [`Renderer::register_native_texture`] are
+    /// sampled with whatever mipmaps their views cover, so generate them
+    /// yourself if needed.
+    pub fn set_texture_mipmaps(&mut self, texture_mipmaps: bool) {
+        self.texture_pool.set_mipmaps(texture_mipmaps);
+    }
+
+    /// Check whether textures uploaded by `egui` are premultiplied on upload.
+    /// See [`Renderer::set_premultiply_textures`].
+    pub fn premultiply_textures(&self) -> bool {
+        self.texture_pool.premultiply()
+    }
+
+    /// Multiply the color of every pixel by its alpha when uploading textures
+    /// from `egui` from now on, in whole and partial updates alike. This is
+    /// off by default, and textures already uploaded are left as they are.
+    ///
+    /// The font atlas is uploaded as white with the coverage of the glyphs in
+    /// alpha, i.e. with straight alpha, which this turns into premultiplied
+    /// coverage for [`BlendMode::PremultipliedAlpha`]. Note that
+    /// [`egui::Color32`] is premultiplied already, so only enable this for
+    /// color images filled with straight alpha, e.g. by passing unmultiplied
+    /// pixels to [`egui::ColorImage::from_rgba_premultiplied`], as their
+    /// color is multiplied twice otherwise. Textures registered with
+    /// [`Renderer::register_native_texture`] are never touched.
+    pub fn set_premultiply_textures(&mut self, premultiply_textures: bool) {
+        self.texture_pool.set_premultiply(premultiply_textures);
+    }
+
+    /// Check whether textures uploaded by `egui` are sampled as sRGB. See
+    /// [`Renderer::set_srgb_textures`].
+    pub fn srgb_textures(&self) -> bool {
+        self.texture_pool.srgb()
+    }
+
+    /// Select whether textures uploaded by `egui` are sampled through a
+    /// `DXGI_FORMAT_R8G8B8A8_UNORM_SRGB` view, which is the default, or a
+    /// `DXGI_FORMAT_R8G8B8A8_UNORM` one. This recreates the views of all
+    /// existing textures, but not the textures themselves.
+    ///
+    /// `egui` describes its own images, including the font atlas, in sRGB,
+    /// and the renderer blends in linear space whether or not
+    /// [`Renderer::set_gamma_output`] is enabled, so keep the default unless
+    /// the images you hand to `egui` already hold linear data. Disabling it
+    /// for those keeps them from being converted twice and looking washed
+    /// out. The format of textures registered with
+    /// [`Renderer::register_native_texture`] is up to their views instead.
+    pub fn set_srgb_textures(&mut self, srgb_textures: bool) -> Result<()> {
+        self.texture_pool.set_srgb(srgb_textures)
+    }
+
+    /// Get the memory budget for native textures. See
+    /// [`Renderer::set_texture_budget`].
+    pub fn texture_budget(&self) -> Option<usize> {
+        self.texture_pool.budget()
+    }
+
+    /// Limit the memory taken by textures registered with
+    /// [`Renderer::register_native_texture`] to `budget` bytes, estimated at 4
+    /// bytes per pixel. `None`, the default, means no limit.
+    ///
+    /// Whenever the budget is exceeded at the start of a frame, the textures
+    /// sampled the longest time ago are freed as if by
+    /// [`Renderer::free_user_texture`], and sampling them afterwards is
+    /// skipped with a warning. Textures uploaded by `egui`, like the font
+    /// atlas, are never evicted, as `egui` would not upload them again.
+    ///
+    /// Use [`Renderer::get_texture_srv`] to check whether a texture is still
+    /// registered before drawing it.
+    pub fn set_texture_budget(&mut self, budget: Option<usize>) {
+        self.texture_pool.set_budget(budget);
+    }
+
+    /// Get the current [`BlendMode`].
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Select how the output is combined with the render target. This
+    /// recreates the blend state of the renderer.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) -> Result<()> {
+        self.update_blend_state(blend_mode, self.alpha_to_coverage)
+    }
+
+    /// Check whether alpha-to-coverage is enabled. See
+    /// [`Renderer::set_alpha_to_coverage`].
+    pub fn alpha_to_coverage(&self) -> bool {
+        self.alpha_to_coverage
+    }
+
+    /// Enable alpha-to-coverage, which turns the alpha of the output into a
+    /// coverage mask over the samples of the render target. This recreates
+    /// the blend state of the renderer.
+    ///
+    /// This only makes a difference when rendering to a multisampled render
+    /// target.
+    pub fn set_alpha_to_coverage(
+        &mut self,
+        alpha_to_coverage: bool,
+    ) -> Result<()> {
+        self.update_blend_state(self.blend_mode, alpha_to_coverage)
+    }
+
+    /// Get the current sample mask. See [`Renderer::set_sample_mask`].
+    pub fn sample_mask(&self) -> u32 {
+        self.sample_mask
+    }
+
+    /// Set the mask of samples that are written when rendering, which is
+    /// passed to `OMSetBlendState`. It defaults to `u32::MAX`, i.e. all
+    /// samples.
+    ///
+    /// This only makes a difference when rendering to a multisampled render
+    /// target.
+    pub fn set_sample_mask(&mut self, sample_mask: u32) {
+        self.sample_mask = sample_mask;
+    }
+
+    /// Get the sample count the renderer is set up for. See
+    /// [`Renderer::set_sample_count`].
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Tell the renderer the number of samples per pixel of the render
+    /// targets it renders to. It defaults to `1`; set it to match a
+    /// multisampled render target, so that the rasterizer state enables
+    /// multisampling. This recreates the rasterizer state of the renderer.
+    ///
+    /// Only whether the count is greater than `1` makes a difference for the
+    /// renderer itself. The render target still has to be resolved before it
+    /// can be presented or read back with [`Renderer::read_into`].
+    pub fn set_sample_count(&mut self, sample_count: u32) -> Result<()> {
+        self.recreate_rasterizer_state(
+            sample_count,
+            self.custom_rasterizer_desc,
+        )?;
+        self.sample_count = sample_count;
+        Ok(())
+    }
+
+    /// Get the custom rasterizer state description, if any. See
+    /// [`Renderer::set_custom_rasterizer_desc`].
+    pub fn custom_rasterizer_desc(&self) -> Option<D3D10_RASTERIZER_DESC> {
+        self.custom_rasterizer_desc
+    }
+
+    /// Replace the rasterizer state of the renderer with one created from
+    /// `desc`, e.g. with `D3D10_FILL_WIREFRAME` to debug the tessellation of
+    /// the UI. Pass `None` to go back to the built-in state. This recreates
+    /// the rasterizer state of the renderer.
+    ///
+    /// `ScissorEnable` is always turned on, as clip rects are applied as
+    /// scissor rects. While a custom state is set,
+    /// [`Renderer::set_sample_count`] no longer changes `MultisampleEnable`.
+    pub fn set_custom_rasterizer_desc(
+        &mut self,
+        desc: Option<D3D10_RASTERIZER_DESC>,
+    ) -> Result<()> {
+        self.recreate_rasterizer_state(self.sample_count, desc)?;
+        self.custom_rasterizer_desc = desc;
+        Ok(())
+    }
+
+    fn recreate_rasterizer_state(
+        &mut self,
+        sample_count: u32,
+        custom: Option<D3D10_RASTERIZER_DESC>,
+    ) -> Result<()> {
+        let mut rasterizer_state = None;
+        unsafe {
+            self.device.CreateRasterizerState(
+                &Self::rasterizer_desc(sample_count, custom),
+                Some(&mut rasterizer_state),
+            )
+        }
+        .map_err(Error::create("rasterizer state"))?;
+        self.rasterizer_state = rasterizer_state.unwrap();
+        if self.debug_names {
+            set_debug_name(&self.rasterizer_state, "egui rasterizer state")?;
+        }
+        Ok(())
+    }
+
+    fn update_blend_state(
+        &mut self,
+        blend_mode: BlendMode,
+        alpha_to_coverage: bool,
+    ) -> Result<()> {
+        let mut blend_state = None;
+        unsafe {
+            self.device.CreateBlendState(
+                &Self::blend_desc(blend_mode, alpha_to_coverage),
+                Some(&mut blend_state),
+            )
+        }
+        .map_err(Error::create("blend state"))?;
+        self.blend_state = blend_state.unwrap();
+        if self.debug_names {
+            set_debug_name(&self.blend_state, "egui blend state")?;
+        }
+        self.blend_mode = blend_mode;
+        self.alpha_to_coverage = alpha_to_coverage;
+        Ok(())
+    }
+
+    /// Get the current mip level of detail bias of the sampler.
+    pub fn mip_lod_bias(&self) -> f32 {
+        self.mip_lod_bias
+    }
+
+    /// Set the bias added to the mip level the sampler picks when sampling
+    /// textures. It defaults to `0.0`; negative values select more detailed
+    /// mip levels for a sharper look, positive values blur. This recreates
+    /// the sampler state of the renderer.
+    ///
+    /// This has no effect on textures without mipmaps. It only matters for
+    /// textures uploaded by `egui` while [`Renderer::set_texture_mipmaps`] is
+    /// enabled, and for native textures with mipmaps registered through
+    /// [`Renderer::register_native_texture`].
+    pub fn set_mip_lod_bias(&mut self, mip_lod_bias: f32) -> Result<()> {
+        self.update_sampler_state(
+            mip_lod_bias,
+            self.sampler_filter,
+            self.address_mode,
+            self.border_color,
+        )
+    }
+
+    /// Get the current [`SamplerFilter`].
+    pub fn sampler_filter(&self) -> SamplerFilter {
+        self.sampler_filter
+    }
+
+    /// Select how textures are filtered when sampled, e.g.
+    /// [`SamplerFilter::Nearest`] to keep pixel art crisp. This applies to
+    /// all textures and recreates the sampler state of the renderer.
+    pub fn set_sampler_filter(
+        &mut self,
+        sampler_filter: SamplerFilter,
+    ) -> Result<()> {
+        self.update_sampler_state(
+            self.mip_lod_bias,
+            sampler_filter,
+            self.address_mode,
+            self.border_color,
+        )
+    }
+
+    /// Get the current [`AddressMode`].
+    pub fn address_mode(&self) -> AddressMode {
+        self.address_mode
+    }
+
+    /// Select how texture coordinates outside of `0.0..=1.0` are resolved
+    /// when sampling. This applies to all textures and recreates the sampler
+    /// state of the renderer.
+    pub fn set_address_mode(
+        &mut self,
+        address_mode: AddressMode,
+    ) -> Result<()> {
+        self.update_sampler_state(
+            self.mip_lod_bias,
+            self.sampler_filter,
+            address_mode,
+            self.border_color,
+        )
+    }
+
+    /// Get the current border color. See [`Renderer::set_border_color`].
+    pub fn border_color(&self) -> egui::Rgba {
+        self.border_color
+    }
+
+    /// Set the color sampled outside of textures with
+    /// [`AddressMode::Border`]. It defaults to [`egui::Rgba::WHITE`]; use
+    /// [`egui::Rgba::TRANSPARENT`] to avoid a light halo around textures
+    /// that are drawn with a filter reaching past their edges. This recreates
+    /// the sampler state of the renderer.
+    ///
+    /// The color is returned by the sampler as is, i.e. it is linear and
+    /// premultiplied like the texels of color textures.
+    pub fn set_border_color(&mut self, border_color: egui::Rgba) -> Result<()> {
+        self.update_sampler_state(
+            self.mip_lod_bias,
+            self.sampler_filter,
+            self.address_mode,
+            border_color,
+        )
+    }
+
+    fn update_sampler_state(
+        &mut self,
+        mip_lod_bias: f32,
+        sampler_filter: SamplerFilter,
+        address_mode: AddressMode,
+        border_color: egui::Rgba,
+    ) -> Result<()> {
+        self.sampler_state = self.create_sampler_state(
+            mip_lod_bias,
+            sampler_filter,
+            address_mode,
+            border_color,
+        )?;
+        // the samplers of single textures only differ in the filter.
+        for (tid, filter) in self.texture_pool.native_sampler_filters() {
+            let sampler = self.create_sampler_state(
+                mip_lod_bias,
+                filter,
+                address_mode,
+                border_color,
+            )?;
+            self.texture_pool
+                .set_native_sampler(tid, Some((filter, sampler)));
+        }
+        self.mip_lod_bias = mip_lod_bias;
+        self.sampler_filter = sampler_filter;
+        self.address_mode = address_mode;
+        self.border_color = border_color;
+        Ok(())
+    }
+
+    fn create_sampler_state(
+        &self,
+        mip_lod_bias: f32,
+        sampler_filter: SamplerFilter,
+        address_mode: AddressMode,
+        border_color: egui::Rgba,
+    ) -> Result<ID3D10SamplerState> {
+        let mut sampler_state = None;
+        unsafe {
+            self.device.CreateSamplerState(
+                &Self::sampler_desc(
+                    mip_lod_bias,
+                    sampler_filter,
+                    address_mode,
+                    border_color,
+                ),
+                Some(&mut sampler_state),
+            )
+        }
+        .map_err(Error::create("sampler state"))?;
+        let sampler_state = sampler_state.unwrap();
+        if self.debug_names {
+            set_debug_name(&sampler_state, "egui sampler state")?;
+        }
+        Ok(sampler_state)
+    }
+
+    /// Check whether vertex positions are snapped to the pixel grid. See
+    /// [`Renderer::set_pixel_snapping`].
+    pub fn pixel_snapping(&self) -> bool {
+        self.pixel_snapping
+    }
+
+    /// Snap vertex positions to the pixel grid of the render target whenever
+    /// the number of physical pixels per point is an integer (e.g. at 100% or
+    /// 200% DPI), which makes text crisper.
+    ///
+    /// This is off by default because it removes the sub-pixel precision that
+    /// keeps slow animations and scrolling smooth.
+    pub fn set_pixel_snapping(&mut self, pixel_snapping: bool) {
+        self.pixel_snapping = pixel_snapping;
+    }
+
+    /// Check whether the vertex and index buffers of meshes are cached. See
+    /// [`Renderer::set_geometry_cache`].
+    pub fn geometry_cache(&self) -> bool {
+        self.geometry_cache.is_some()
+    }
+
+    /// Keep the vertices and indices of the meshes drawn in immutable
+    /// buffers, keyed by a hash of their content, and draw from them again
+    /// whenever the same meshes come back instead of uploading them anew.
+    /// This is off by default.
+    ///
+    /// For a mostly static UI, this saves writing the same geometry to the
+    /// dynamic buffers every frame, at the cost of hashing every mesh, and of
+    /// creating new buffers for every mesh that changes. Buffers not drawn
+    /// from for a few frames are released. Disabling the cache releases all
+    /// of them at once.
+    pub fn set_geometry_cache(&mut self, geometry_cache: bool) {
+        if geometry_cache != self.geometry_cache.is_some() {
+            self.geometry_cache = geometry_cache.then(GeometryCache::default);
+        }
+    }
+
+    /// Check whether the pipeline state is restored after rendering. See
+    /// [`Renderer::set_restore_state_after_render`].
+    pub fn restore_state_after_render(&self) -> bool {
+        self.restore_state_after_render
+    }
+
+    /// Capture the pipeline state with a [`StateBlock`] before rendering and
+    /// apply it again afterwards, so that rendering leaves the state of the
+    /// device context as it was. This is off by default; see
+    /// [`Renderer::render`] for the state that is overridden otherwise.
+    pub fn set_restore_state_after_render(
+        &mut self,
+        restore_state_after_render: bool,
+    ) {
+        self.restore_state_after_render = restore_state_after_render;
+    }
+
+    /// Capture the pipeline state of `device_context` now and apply it again
+    /// when the returned [`StateGuard`] is dropped. Unlike
+    /// [`Renderer::set_restore_state_after_render`], this covers a whole scope,
+    /// e.g. several render calls along with draw calls of your own, and
+    /// restores the state on early returns as well.
+    pub fn state_guard<'a>(
+        &self,
+        device_context: &'a ID3D10Device,
+    ) -> StateGuard<'a> {
+        StateGuard::new(device_context)
+    }
+
+    /// Send the warnings of the renderer, e.g. about textures `egui` wants to
+    /// sample but never uploaded, to `handler` instead of the `log` crate.
+    /// Pass `None` to send them to `log` again, which is the default. With the
+    /// default `logging` feature disabled, warnings are dropped unless a
+    /// handler is set.
+    ///
+    /// Each warning is a single line of text without a trailing newline.
+    pub fn set_diagnostic_handler(
+        &mut self,
+        handler: Option<Box<DiagnosticHandler>>,
+    ) {
+        self.diagnostics = Diagnostics(handler.map(Rc::from));
+        self.texture_pool.set_diagnostics(self.diagnostics.clone());
+    }
+
+    /// Check whether Direct3D resources created by the renderer are given
+    /// debug names. See [`Renderer::set_debug_names`].
+    pub fn debug_names(&self) -> bool {
+        self.debug_names
+    }
+
+    /// Give every Direct3D resource created by the renderer a debug name
+    /// (`WKPDID_D3DDebugObjectName`), which shows up in the debug layer
+    /// messages and in graphics debuggers like RenderDoc or PIX. This is off
+    /// by default because it adds a little overhead to every resource
+    /// creation.
+    ///
+    /// Enabling it also names the resources that already exist. All names
+    /// start with `egui`:
+    /// + `egui input layout`, `egui vertex shader`,
+    ///   `egui linear pixel shader`, `egui gamma pixel shader`,
+    ///   `egui rasterizer state`, `egui sampler state` and `egui blend state`;
+    /// + `egui vertex buffer`, `egui index buffer` and
+    ///   `egui constant buffer`;
+    /// + `egui font atlas` for the font texture, and `egui texture <n>` for
+    ///   the other textures uploaded by `egui`, where `<n>` is the number in
+    ///   their [`egui::TextureId::Managed`]. Their shader resource views share
+    ///   the same name.
+    ///
+    /// Textures registered with [`Renderer::register_native_texture`] are
+    /// left alone.
+    pub fn set_debug_names(&mut self, debug_names: bool) -> Result<()> {
+        if debug_names {
+            set_debug_name(&self.input_layout, "egui input layout")?;
+            set_debug_name(&self.vertex_shader, "egui vertex shader")?;
+            set_debug_name(&self.transform_buffer, "egui constant buffer")?;
+            set_debug_name(
+                &self.linear_pixel_shader,
+                "egui linear pixel shader",
+            )?;
+            set_debug_name(
+                &self.gamma_pixel_shader,
+                "egui gamma pixel shader",
+            )?;
+            set_debug_name(&self.rasterizer_state, "egui rasterizer state")?;
+            set_debug_name(&self.sampler_state, "egui sampler state")?;
+            set_debug_name(&self.blend_state, "egui blend state")?;
+            if let Some(vertex_buffer) = &self.vertex_buffer {
+                set_debug_name(vertex_buffer, "egui vertex buffer")?;
+            }
+            if let Some(index_buffer) = &self.index_buffer {
+                set_debug_name(index_buffer, "egui index buffer")?;
+            }
+        }
+        self.texture_pool.set_debug_names(debug_names)?;
+        self.debug_names = debug_names;
+        Ok(())
+    }
+
+    /// Check whether unsupported paint callbacks are drawn as magenta quads.
+    /// See [`Renderer::set_debug_unsupported_callbacks`].
+    pub fn debug_unsupported_callbacks(&self) -> bool {
+        self.debug_unsupported_callbacks
+    }
+
+    /// Fill the rect of every paint callback that is not a
+    /// [`PaintCallbackFn`], and thus cannot be run by the renderer, with
+    /// solid magenta, clipped to its clip rect. This is off by default, in
+    /// which case such callbacks leave their rect blank and only a warning
+    /// is logged.
+    pub fn set_debug_unsupported_callbacks(
+        &mut self,
+        debug_unsupported_callbacks: bool,
+    ) {
+        self.debug_unsupported_callbacks = debug_unsupported_callbacks;
     }
 
     /// Render the output of `egui` to the provided render target using the
     /// provided device context. The render target should use a linear color
-    /// space (e.g. `DXGI_FORMAT_R8G8B8A8_UNORM_SRGB`) for proper results.
+    /// space (e.g. `DXGI_FORMAT_R8G8B8A8_UNORM_SRGB`) for proper results,
+    /// unless [`Renderer::set_gamma_output`] is enabled. A warning is logged
+    /// once if a plain `UNORM` render target is used without it.
     ///
     /// The `scale_factor` should be the scale factor of your window and not
     /// confused with [`egui::Context::zoom_factor`]. If you are using `winit`,
-    /// the `scale_factor` can be aquired using `Window::scale_factor`.
+    /// the `scale_factor` can be aquired using `Window::scale_factor`. The
+    /// renderer multiplies it with the zoom factor of `egui_ctx` to get the
+    /// number of physical pixels per point. Vertex positions (in the vertex
+    /// shader) and clip rects (for the scissor rects) are both converted from
+    /// points to physical pixels by multiplying them with this one product,
+    /// exactly once, so they agree for any combination of scale factor and
+    /// zoom factor. The render target is expected to be sized in physical
+    /// pixels, i.e. the screen rect of `egui` times that product. To rely on
+    /// [`egui::FullOutput::pixels_per_point`] instead, use
+    /// [`Renderer::render_full_output`].
+    ///
+    /// ## Texture Updates
+    ///
+    /// The textures delta of `egui_output` is always applied first, even if
+    /// there is nothing to draw, e.g. because there are no shapes or the
+    /// render target has a size of zero, and even if rendering fails later
+    /// on. `egui` sends every delta only once, so skipping it would lose
+    /// textures. To apply a delta without drawing at all, e.g. to upload
+    /// textures in one frame and draw in a later one, use
+    /// [`Renderer::upload_textures`].
     ///
     /// ## Error Handling
     ///
     /// If any Direct3D resource creation fails, this function will return an
     /// error. In this case you may have a incomplete or incorrect rendering
-    /// result. You can create the Direct3D10 device with debug layer
-    /// enabled to find out details on the error.
-    /// If the device has been lost, you should drop the [`Renderer`] and create
-    /// a new one.
+    /// result. It also fails with [`Error::InvalidScaleFactor`], without
+    /// drawing anything, if `scale_factor` times the zoom factor is not a
+    /// positive finite number, e.g. when a platform integration reports a
+    /// scale factor of `0.0`. You can create the Direct3D10 device with debug
+    /// layer enabled to find out details on the error.
+    ///
+    /// If the device has been lost, as told by the error code or by
+    /// `GetDeviceRemovedReason`, the error is [`Error::DeviceLost`]. Create a
+    /// new device and move the renderer to it with [`Renderer::reset_device`]
+    /// instead of retrying.
     ///
     /// ## Pipeline State Management
     ///
     /// This function sets up its own Direct3D10 pipeline state for rendering on
     /// the provided device context. It assumes that the hull shader, domain
     /// shader and geometry shader stages are not active on the provided device
-    /// context without any further checks. It is all *your* responsibility to
-    /// backup the current pipeline state and restore it afterwards if your
-    /// rendering pipeline depends on it.
+    /// context without any further checks. Unless
+    /// [`Renderer::set_restore_state_after_render`] is enabled, it is all
+    /// *your* responsibility to backup the current pipeline state and restore
+    /// it afterwards if your rendering pipeline depends on it.
     ///
     /// Particularly, it overrides:
     /// + The input layout, vertex buffer, index buffer and primitive topology
     ///   in the input assembly stage;
-    /// + The current shader in the vertex shader stage;
-    /// + The viewport and rasterizer state in the rasterizer stage;
+    /// + The current shader and constant buffer slot 0 in the vertex shader
+    ///   stage;
+    /// + The viewport, scissor rect and rasterizer state in the rasterizer
+    ///   stage;
     /// + The current shader, shader resource slot 0 and sampler slot 0 in the
     ///   pixel shader stage;
     /// + The render target(s) and blend state in the output merger stage;
     ///
-    /// See the [`egui-demo`](https://github.com/Nekomaru-PKU/egui-directx10/blob/main/examples/egui-demo.rs)
-    /// example for code examples.
-    pub fn render(
-        &mut self,
-        device_context: &ID3D10Device,
-        render_target: &ID3D10RenderTargetView,
+    /// A [`StateBlock`] captured before rendering restores all of these.
+    /// Without it, the scissor rect is left covering the whole render target
+    /// rather than the clip rect of the last mesh drawn.
+    ///
+    /// See the [`egui-demo`](https://github.com/Nekomaru-PKU/egui-directx10/blob/main/examples/egui-demo.rs)
+    /// example for code examples.
+    pub fn render(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_target: &ID3D10RenderTargetView,
+        egui_ctx: &egui::Context,
+        egui_output: RendererOutput,
+        scale_factor: f32,
+    ) -> Result<()> {
+        let primitives = egui_ctx
+            .tessellate(egui_output.shapes, egui_output.pixels_per_point);
+        self.render_impl(
+            device_context,
+            std::slice::from_ref(render_target),
+            None,
+            egui_output.textures_delta,
+            &primitives,
+            scale_factor * egui_ctx.zoom_factor(),
+            None,
+        )
+    }
+
+    /// Same as [`Renderer::render`], but render into `viewport`, a
+    /// sub-rectangle of the render target in physical pixels, instead of the
+    /// whole render target, e.g. to composite the UI into a corner of a
+    /// larger frame.
+    ///
+    /// The UI is laid out as if `viewport` was the whole screen, so the
+    /// screen rect of `egui` should match its size. Nothing outside of
+    /// `viewport` is drawn to, including by paint callbacks.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidArgument`] if `viewport` does not lie within
+    /// the render target.
+    pub fn render_to_viewport(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_target: &ID3D10RenderTargetView,
+        viewport: RECT,
+        egui_ctx: &egui::Context,
+        egui_output: RendererOutput,
+        scale_factor: f32,
+    ) -> Result<()> {
+        let primitives = egui_ctx
+            .tessellate(egui_output.shapes, egui_output.pixels_per_point);
+        self.render_impl(
+            device_context,
+            std::slice::from_ref(render_target),
+            Some(viewport),
+            egui_output.textures_delta,
+            &primitives,
+            scale_factor * egui_ctx.zoom_factor(),
+            None,
+        )
+    }
+
+    /// Same as [`Renderer::render`], but render into several render targets at
+    /// once, e.g. a color target and an id buffer of a deferred compositor.
+    ///
+    /// Every render target receives the same output, blended with the current
+    /// [`BlendMode`] and with all color channels written. They must all have
+    /// the same size, as required by Direct3D10. The first one is the one
+    /// whose format is checked, see [`Renderer::render`]. Meshes sampling any
+    /// of them are skipped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidArgument`] if `render_targets` is empty or
+    /// holds more than `D3D10_SIMULTANEOUS_RENDER_TARGET_COUNT` render
+    /// targets.
+    pub fn render_to_targets(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_targets: &[ID3D10RenderTargetView],
+        egui_ctx: &egui::Context,
+        egui_output: RendererOutput,
+        scale_factor: f32,
+    ) -> Result<()> {
+        let primitives = egui_ctx
+            .tessellate(egui_output.shapes, egui_output.pixels_per_point);
+        self.render_impl(
+            device_context,
+            render_targets,
+            None,
+            egui_output.textures_delta,
+            &primitives,
+            scale_factor * egui_ctx.zoom_factor(),
+            None,
+        )
+    }
+
+    /// Render a whole frame to the back buffer of `swap_chain`: clear it to
+    /// `clear_color`, render `egui_output` over it as [`Renderer::render`]
+    /// does and present it with `sync_interval`.
+    ///
+    /// This suits applications that draw nothing but the UI. To draw
+    /// anything else, or to render into a target of your own, use
+    /// [`Renderer::render`] instead. The render target view of the back
+    /// buffer is created on every call and dropped before presenting, so
+    /// the swap chain can be resized between frames as usual.
+    ///
+    /// Everything is done on the device of the renderer, see
+    /// [`Renderer::device`], which has to be the device of `swap_chain`.
+    pub fn render_frame(
+        &mut self,
+        swap_chain: &IDXGISwapChain,
+        egui_ctx: &egui::Context,
+        egui_output: RendererOutput,
+        scale_factor: f32,
+        clear_color: [f32; 4],
+        sync_interval: u32,
+    ) -> Result<()> {
+        let back_buffer =
+            unsafe { swap_chain.GetBuffer::<ID3D10Texture2D>(0) }?;
+        let mut render_target = None;
+        unsafe {
+            self.device.CreateRenderTargetView(
+                &back_buffer,
+                None,
+                Some(&mut render_target),
+            )
+        }
+        .map_err(Error::create("render target view"))?;
+        let render_target = render_target.unwrap();
+        let device = self.device.clone();
+        unsafe { device.ClearRenderTargetView(&render_target, &clear_color) };
+        self.render(
+            &device,
+            &render_target,
+            egui_ctx,
+            egui_output,
+            scale_factor,
+        )?;
+        drop(render_target);
+        unsafe { swap_chain.Present(sync_interval, DXGI_PRESENT(0)) }.ok()?;
+        Ok(())
+    }
+
+    /// Render primitives tessellated ahead of time, e.g. on a worker thread
+    /// with [`egui::Context::tessellate`], so that the render thread does not
+    /// need to.
+    ///
+    /// `pixels_per_point` is the number of physical pixels of the render
+    /// target per point, i.e. the native scale factor times
+    /// [`egui::Context::zoom_factor`], as in
+    /// [`egui::FullOutput::pixels_per_point`]. It should match the value the
+    /// primitives were tessellated with.
+    ///
+    /// Everything else, including error handling and pipeline state
+    /// management, is the same as for [`Renderer::render`].
+    pub fn render_primitives(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_target: &ID3D10RenderTargetView,
+        primitives: &[ClippedPrimitive],
+        textures_delta: TexturesDelta,
+        pixels_per_point: f32,
+    ) -> Result<()> {
+        self.render_impl(
+            device_context,
+            std::slice::from_ref(render_target),
+            None,
+            textures_delta,
+            primitives,
+            pixels_per_point,
+            None,
+        )
+    }
+
+    /// Render a whole [`egui::FullOutput`] to the provided render target and
+    /// hand back the parts of it meant for the platform integration, i.e.
+    /// [`egui::FullOutput::platform_output`] and
+    /// [`egui::FullOutput::viewport_output`].
+    ///
+    /// Unlike [`Renderer::render`], this does not take a `scale_factor`.
+    /// Positions and clip rects from `egui` are converted to physical pixels
+    /// by multiplying them with [`egui::FullOutput::pixels_per_point`] exactly
+    /// once, as `egui`'s own reference renderers do. That value already
+    /// includes both the native scale factor and
+    /// [`egui::Context::zoom_factor`], so there is no risk of applying the
+    /// zoom twice.
+    ///
+    /// Everything else, including error handling and pipeline state
+    /// management, is the same as for [`Renderer::render`].
+    pub fn render_full_output(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_target: &ID3D10RenderTargetView,
+        egui_ctx: &egui::Context,
+        full_output: egui::FullOutput,
+    ) -> Result<(
+        egui::PlatformOutput,
+        egui::ViewportIdMap<egui::ViewportOutput>,
+    )> {
+        let SplitOutput {
+            renderer: egui_output,
+            platform: platform_output,
+            viewports: viewport_output,
+        } = split_output(full_output);
+        let pixels_per_point = egui_output.pixels_per_point;
+        let primitives =
+            egui_ctx.tessellate(egui_output.shapes, pixels_per_point);
+        self.render_impl(
+            device_context,
+            std::slice::from_ref(render_target),
+            None,
+            egui_output.textures_delta,
+            &primitives,
+            pixels_per_point,
+            None,
+        )?;
+        Ok((platform_output, viewport_output))
+    }
+
+    /// Same as [`Renderer::render`], but additionally returns every mesh that
+    /// was drawn, in draw order.
+    ///
+    /// This is intended for tooling and tests that want to inspect vertex
+    /// counts, clip rects and texture references without reading back
+    /// pixels. Collecting the meshes has a cost, so prefer
+    /// [`Renderer::render`] for normal rendering. See [`MeshData`] for the
+    /// stability of the returned data.
+    pub fn render_with_draw_list(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_target: &ID3D10RenderTargetView,
+        egui_ctx: &egui::Context,
+        egui_output: RendererOutput,
+        scale_factor: f32,
+    ) -> Result<Vec<MeshData>> {
+        let mut draw_list = Vec::new();
+        let primitives = egui_ctx
+            .tessellate(egui_output.shapes, egui_output.pixels_per_point);
+        self.render_impl(
+            device_context,
+            std::slice::from_ref(render_target),
+            None,
+            egui_output.textures_delta,
+            &primitives,
+            scale_factor * egui_ctx.zoom_factor(),
+            Some(&mut draw_list),
+        )?;
+        Ok(draw_list)
+    }
+
+    /// Get the [`RenderStats`] of the most recent call to any of the render
+    /// functions, e.g. to graph the cost of the UI over time.
+    pub fn last_render_stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Check whether the GPU time of rendering is measured. See
+    /// [`Renderer::set_gpu_timing`].
+    pub fn gpu_timing(&self) -> bool {
+        self.gpu_timer.is_some()
+    }
+
+    /// Measure how long the GPU takes for the draw calls of every render
+    /// call with timestamp queries, to be read with
+    /// [`Renderer::last_gpu_time`]. This is off by default, as it issues
+    /// three queries per render call. Disabling it drops the measurements.
+    pub fn set_gpu_timing(&mut self, gpu_timing: bool) {
+        if gpu_timing != self.gpu_timer.is_some() {
+            self.gpu_timer = gpu_timing.then(GpuTimer::default);
+        }
+    }
+
+    /// Get the time the GPU took for the draw calls of the most recent
+    /// render call it has finished, see [`Renderer::set_gpu_timing`].
+    ///
+    /// The GPU runs a few frames behind, so this usually reports a frame
+    /// rendered earlier than the last one, and `None` until the first
+    /// measurement is available or while timing is disabled. This never
+    /// waits for the GPU. Measurements during which the GPU changed its
+    /// clock, e.g. because of power management, are discarded. Paint
+    /// callbacks are included in the time.
+    pub fn last_gpu_time(&mut self) -> Option<std::time::Duration> {
+        self.gpu_timer.as_mut()?.last()
+    }
+
+    /// Measure what rendering `egui_output` would cost for a render target
+    /// of `frame_size` physical pixels, without touching the GPU or needing
+    /// a render target.
+    ///
+    /// The shapes are tessellated and prepared the same way
+    /// [`Renderer::render`] does, and meshes it would skip, e.g. because they
+    /// are clipped out of the frame, are not counted. Nothing is drawn and no
+    /// texture is uploaded, so `egui_output` can still be rendered
+    /// afterwards. This is meant for development tools, e.g. to flag
+    /// expensive UIs in CI or in an in-app profiler. Warnings about meshes
+    /// that are skipped go to the handler set with
+    /// [`Renderer::set_diagnostic_handler`], as they do while rendering.
+    pub fn estimate_cost(
+        &self,
+        egui_ctx: &egui::Context,
+        egui_output: &RendererOutput,
+        scale_factor: f32,
+        frame_size: (u32, u32),
+    ) -> CostEstimate {
+        let mut cost = CostEstimate {
+            texture_upload_bytes: egui_output
+                .textures_delta
+                .set
+                .iter()
+                .map(|(_, delta)| {
+                    delta.image.width()
+                        * delta.image.height()
+                        * mem::size_of::<egui::Color32>()
+                })
+                .sum(),
+            ..Default::default()
+        };
+        let primitives = egui_ctx.tessellate(
+            egui_output.shapes.clone(),
+            egui_output.pixels_per_point,
+        );
+        let (meshes, _) = Self::prepare_primitives(
+            &primitives,
+            scale_factor * egui_ctx.zoom_factor(),
+            frame_size,
+            &self.diagnostics,
+        );
+        for mesh in &meshes {
+            cost.mesh_count += 1;
+            cost.vertex_count += mesh.mesh.vertices.len();
+            cost.index_count += mesh.indices.len();
+        }
+        cost
+    }
+
+    /// Tessellate `egui_output` and prepare its meshes exactly as
+    /// [`Renderer::render`] would for a render target of `frame_size`
+    /// physical pixels, without touching the GPU.
+    ///
+    /// Meshes that [`Renderer::render`] would skip, e.g. because they are
+    /// clipped out of the frame, are left out, and so are paint callbacks.
+    /// This is meant for tools and tests that inspect or cache the geometry.
+    /// See [`MeshData`] for the stability of the returned data. Warnings
+    /// about meshes that are skipped go to the handler set with
+    /// [`Renderer::set_diagnostic_handler`], as they do while rendering.
+    pub fn prepare_meshes(
+        &self,
         egui_ctx: &egui::Context,
-        egui_output: RendererOutput,
+        egui_output: &RendererOutput,
         scale_factor: f32,
+        frame_size: (u32, u32),
+    ) -> Vec<MeshData> {
+        let primitives = egui_ctx.tessellate(
+            egui_output.shapes.clone(),
+            egui_output.pixels_per_point,
+        );
+        Self::prepare_primitives(
+            &primitives,
+            scale_factor * egui_ctx.zoom_factor(),
+            frame_size,
+            &self.diagnostics,
+        )
+        .0
+        .iter()
+        .map(PreparedMesh::to_mesh_data)
+        .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_impl(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_targets: &[ID3D10RenderTargetView],
+        viewport: Option<RECT>,
+        textures_delta: TexturesDelta,
+        primitives: &[ClippedPrimitive],
+        pixels_per_point: f32,
+        draw_list: Option<&mut Vec<MeshData>>,
+    ) -> Result<()> {
+        self.try_render(
+            device_context,
+            render_targets,
+            viewport,
+            textures_delta,
+            primitives,
+            pixels_per_point,
+            draw_list,
+        )
+        .map_err(|error| match error {
+            Error::DeviceLost(_) => error,
+            // the device may fail calls in other ways once it is lost.
+            _ => match unsafe { self.device.GetDeviceRemovedReason() } {
+                Err(reason) => Error::DeviceLost(reason),
+                Ok(()) => error,
+            },
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn try_render(
+        &mut self,
+        device_context: &ID3D10Device,
+        // all of the same size, the first one being the color target
+        render_targets: &[ID3D10RenderTargetView],
+        // the whole render target if `None`
+        viewport: Option<RECT>,
+        textures_delta: TexturesDelta,
+        primitives: &[ClippedPrimitive],
+        // physical pixels of the render target per egui point, which is the
+        // only factor applied to both vertex positions and clip rects
+        pixels_per_point: f32,
+        draw_list: Option<&mut Vec<MeshData>>,
     ) -> Result<()> {
-        self.texture_pool
-            .update(device_context, egui_output.textures_delta)?;
+        self.stats = RenderStats::default();
+        self.stats.textures =
+            self.texture_pool.update(device_context, textures_delta)?;
 
-        if egui_output.shapes.is_empty() {
-            return Ok(());
+        if !(pixels_per_point > 0.0 && pixels_per_point.is_finite()) {
+            return Err(Error::InvalidScaleFactor(pixels_per_point));
         }
 
-        let frame_size = Self::get_render_target_size(render_target)?;
-        let frame_size_scaled = (
-            frame_size.0 as f32 / scale_factor,
-            frame_size.1 as f32 / scale_factor,
-        );
-        let zoom_factor = egui_ctx.zoom_factor();
-
-        self.setup(device_context, render_target, frame_size);
-        let meshes = egui_ctx
-            .tessellate(egui_output.shapes, egui_output.pixels_per_point)
-            .into_iter()
-            .filter_map(
-                |ClippedPrimitive {
-                     primitive,
-                     clip_rect,
-                 }| match primitive {
-                    Primitive::Mesh(mesh) => Some((mesh, clip_rect)),
-                    Primitive::Callback(..) => {
-                        log::warn!("paint callbacks are not yet supported.");
-                        None
-                    },
-                },
-            )
-            .filter_map(|(mesh, clip_rect)| {
-                if mesh.indices.is_empty() {
-                    return None;
+        let (Some(render_target), 1..=MAX_RENDER_TARGETS) =
+            (render_targets.first(), render_targets.len())
+        else {
+            return Err(Error::InvalidArgument(format!(
+                "invalid number of render targets {}",
+                render_targets.len()
+            )));
+        };
+
+        // a minimized window may come with a render target of zero size,
+        // which nothing can be drawn to. textures are still updated above,
+        // as egui will not send the same delta again.
+        let target_size = render_target_size(render_target)?;
+        let frame = match viewport {
+            None => Frame {
+                offset: (0, 0),
+                size: target_size,
+            },
+            Some(rect) => {
+                if rect.left < 0
+                    || rect.top < 0
+                    || rect.right < rect.left
+                    || rect.bottom < rect.top
+                    || rect.right as u32 > target_size.0
+                    || rect.bottom as u32 > target_size.1
+                {
+                    return Err(Error::InvalidArgument(format!(
+                        "viewport {:?} is not within the render target",
+                        (rect.left, rect.top, rect.right, rect.bottom)
+                    )));
                 }
-                if mesh.indices.len() % 3 != 0 {
-                    log::warn!(concat!(
-                        "egui wants to draw a incomplete triangle. ",
-                        "this request will be ignored."
-                    ));
-                    return None;
+                Frame {
+                    offset: (rect.left, rect.top),
+                    size: (
+                        (rect.right - rect.left) as u32,
+                        (rect.bottom - rect.top) as u32,
+                    ),
                 }
-                Some(MeshData {
-                    vtx: mesh
-                        .vertices
-                        .into_iter()
-                        .map(|Vertex { pos, uv, color }| VertexData {
-                            pos: Pos2::new(
-                                pos.x * zoom_factor / frame_size_scaled.0 * 2.0
-                                    - 1.0,
-                                1.0 - pos.y * zoom_factor / frame_size_scaled.1
-                                    * 2.0,
-                            ),
-                            uv,
-                            color: color.into(),
-                        })
-                        .collect(),
-                    idx: mesh.indices,
-                    tex: mesh.texture_id,
-                    clip_rect: clip_rect * scale_factor * zoom_factor,
-                })
-            });
-        for mesh in meshes {
-            Self::draw_mesh(
-                &self.device,
+            },
+        };
+        if let Some(clear_color) = &self.clear_color {
+            for render_target in render_targets {
+                unsafe {
+                    device_context
+                        .ClearRenderTargetView(render_target, clear_color)
+                };
+            }
+        }
+        if primitives.is_empty() || frame.size.0 == 0 || frame.size.1 == 0 {
+            return Ok(());
+        }
+
+        // issued before anything that has to be undone on an error.
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin(device_context)?;
+        }
+        let keyed_mutexes =
+            self.texture_pool
+                .acquire_shared(primitives.iter().filter_map(
+                    |ClippedPrimitive { primitive, .. }| match primitive {
+                        Primitive::Mesh(mesh) => Some(mesh.texture_id),
+                        Primitive::Callback(_) => None,
+                    },
+                ))?;
+        let state_block = self
+            .restore_state_after_render
+            .then(|| StateBlock::capture(device_context));
+        let result = self.draw_primitives(
+            device_context,
+            render_targets,
+            frame,
+            primitives,
+            pixels_per_point,
+            draw_list,
+        );
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.end();
+        }
+        if let Some(state_block) = state_block {
+            state_block.apply(device_context);
+        } else {
+            // the scissor rect of the last mesh would otherwise clip whatever
+            // the caller draws next.
+            unsafe {
+                device_context.RSSetScissorRects(Some(&[RECT {
+                    left: 0,
+                    top: 0,
+                    right: target_size.0 as _,
+                    bottom: target_size.1 as _,
+                }]))
+            };
+        }
+        TexturePool::release_shared(&keyed_mutexes);
+        if let Some(geometry_cache) = &mut self.geometry_cache {
+            geometry_cache.end_frame();
+        }
+        result
+    }
+
+    fn draw_primitives(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_targets: &[ID3D10RenderTargetView],
+        frame: Frame,
+        primitives: &[ClippedPrimitive],
+        pixels_per_point: f32,
+        mut draw_list: Option<&mut Vec<MeshData>>,
+    ) -> Result<()> {
+        self.check_render_target_format(&render_targets[0]);
+        let snap_to_pixels =
+            self.pixel_snapping && pixels_per_point.fract() == 0.0;
+
+        let target_resources = render_targets
+            .iter()
+            .map(|render_target| unsafe { render_target.GetResource() })
+            .collect::<windows::core::Result<Vec<_>>>()?;
+
+        self.write_transform(frame.size, pixels_per_point, snap_to_pixels)?;
+        self.setup(device_context, render_targets, frame);
+        let (meshes, callbacks) = Self::prepare_primitives(
+            primitives,
+            pixels_per_point,
+            frame.size,
+            &self.diagnostics,
+        );
+
+        // A full-screen UI usually has every mesh clipped to the whole frame,
+        // in which case neither per-mesh scissor rects nor buffers are needed.
+        if callbacks.is_empty()
+            && meshes.iter().all(|mesh| {
+                let rect = Self::scissor_rect(mesh.clip_rect, frame.size);
+                rect.left == 0
+                    && rect.top == 0
+                    && rect.right == frame.size.0 as _
+                    && rect.bottom == frame.size.1 as _
+            })
+        {
+            self.draw_batched(
                 device_context,
-                &self.texture_pool,
-                mesh,
+                &target_resources,
+                frame,
+                &meshes,
+                draw_list,
             )?;
+        } else {
+            let mut callbacks = callbacks.into_iter().peekable();
+            for (i, mesh) in meshes.iter().enumerate() {
+                while let Some((_, callback, clip_rect)) =
+                    callbacks.next_if(|(at, ..)| *at == i)
+                {
+                    self.call_paint_callback(
+                        device_context,
+                        render_targets,
+                        &target_resources,
+                        frame,
+                        pixels_per_point,
+                        &callback,
+                        clip_rect,
+                    )?;
+                }
+                if self.draw_mesh(
+                    device_context,
+                    &target_resources,
+                    frame,
+                    mesh,
+                )? {
+                    if let Some(draw_list) = draw_list.as_deref_mut() {
+                        draw_list.push(mesh.to_mesh_data());
+                    }
+                }
+            }
+            for (_, callback, clip_rect) in callbacks {
+                self.call_paint_callback(
+                    device_context,
+                    render_targets,
+                    &target_resources,
+                    frame,
+                    pixels_per_point,
+                    &callback,
+                    clip_rect,
+                )?;
+            }
         }
-
         Ok(())
     }
 
+    /// Collect the meshes among `primitives`, skipping the ones that would not
+    /// draw anything. Paint callbacks are returned separately, each with the
+    /// number of meshes preceding it.
+    fn prepare_primitives<'a>(
+        primitives: &'a [ClippedPrimitive],
+        pixels_per_point: f32,
+        frame_size: (u32, u32),
+        diagnostics: &Diagnostics,
+    ) -> (
+        Vec<PreparedMesh<'a>>,
+        Vec<(usize, egui::PaintCallback, egui::Rect)>,
+    ) {
+        let mut meshes = Vec::new();
+        let mut callbacks = Vec::new();
+        for ClippedPrimitive {
+            primitive,
+            clip_rect,
+        } in primitives
+        {
+            let mesh = match primitive {
+                Primitive::Mesh(mesh) => mesh,
+                Primitive::Callback(callback) => {
+                    callbacks.push((
+                        meshes.len(),
+                        callback.clone(),
+                        *clip_rect,
+                    ));
+                    continue;
+                },
+            };
+            // an incomplete trailing triangle is dropped, but the rest of
+            // the mesh is still drawn.
+            let index_count = mesh.indices.len() - mesh.indices.len() % 3;
+            if index_count != mesh.indices.len() {
+                warn!(
+                    diagnostics,
+                    concat!(
+                        "egui wants to draw a incomplete triangle. ",
+                        "its {} trailing indices will be ignored."
+                    ),
+                    mesh.indices.len() - index_count
+                );
+            }
+            // a mesh without vertices has nothing to draw, even if it has
+            // indices, and would leave the vertex buffer empty.
+            if index_count == 0 || mesh.vertices.is_empty() {
+                continue;
+            }
+            // egui still emits content that is scrolled out of view, which
+            // would only cost buffer uploads and draw calls.
+            let clip_rect = *clip_rect * pixels_per_point;
+            let scissor_rect = Self::scissor_rect(clip_rect, frame_size);
+            if scissor_rect.left >= scissor_rect.right
+                || scissor_rect.top >= scissor_rect.bottom
+            {
+                continue;
+            }
+            // an index past the vertices reads out of bounds on the GPU.
+            // only checked in debug builds, as egui itself never emits one.
+            #[cfg(debug_assertions)]
+            if let Some(&index) = mesh.indices[..index_count]
+                .iter()
+                .find(|&&index| index as usize >= mesh.vertices.len())
+            {
+                warn!(
+                    diagnostics,
+                    concat!(
+                        "egui wants to draw a mesh with index {} out of its ",
+                        "{} vertices. this request will be ignored."
+                    ),
+                    index,
+                    mesh.vertices.len()
+                );
+                continue;
+            }
+            meshes.push(PreparedMesh {
+                mesh,
+                indices: &mesh.indices[..index_count],
+                clip_rect,
+            });
+        }
+        (meshes, callbacks)
+    }
+
     fn setup(
         &mut self,
         ctx: &ID3D10Device,
-        render_target: &ID3D10RenderTargetView,
-        frame_size: (u32, u32),
+        render_targets: &[ID3D10RenderTargetView],
+        frame: Frame,
     ) {
+        let mut targets: [Option<ID3D10RenderTargetView>; MAX_RENDER_TARGETS] =
+            Default::default();
+        for (slot, render_target) in targets.iter_mut().zip(render_targets) {
+            *slot = Some(render_target.clone());
+        }
         unsafe {
             ctx.IASetPrimitiveTopology(D3D10_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
             ctx.IASetInputLayout(&self.input_layout);
             ctx.VSSetShader(&self.vertex_shader);
-            ctx.PSSetShader(&self.pixel_shader);
+            ctx.VSSetConstantBuffers(
+                0,
+                Some(&[Some(self.transform_buffer.clone())]),
+            );
+            ctx.PSSetShader(
+                if self.gamma_output && self.hdr_white_level.is_none() {
+                    &self.gamma_pixel_shader
+                } else {
+                    &self.linear_pixel_shader
+                },
+            );
             ctx.RSSetState(&self.rasterizer_state);
             ctx.RSSetViewports(Some(&[D3D10_VIEWPORT {
-                TopLeftX: 0,
-                TopLeftY: 0,
-                Width: frame_size.0 as _,
-                Height: frame_size.1 as _,
+                TopLeftX: frame.offset.0,
+                TopLeftY: frame.offset.1,
+                Width: frame.size.0 as _,
+                Height: frame.size.1 as _,
                 MinDepth: 0.,
                 MaxDepth: 1.,
             }]));
             ctx.PSSetSamplers(0, Some(&[Some(self.sampler_state.clone())]));
-            ctx.OMSetRenderTargets(Some(&[Some(render_target.clone())]), None);
-            ctx.OMSetBlendState(&self.blend_state, &[0.; 4], u32::MAX);
+            ctx.OMSetRenderTargets(
+                Some(&targets[..render_targets.len()]),
+                None,
+            );
+            ctx.OMSetBlendState(&self.blend_state, &[0.; 4], self.sample_mask);
         }
     }
 
-    fn draw_mesh(
-        device: &ID3D10Device,
-        device_context: &ID3D10Device,
-        texture_pool: &TexturePool,
-        mesh: MeshData,
+    /// Update the constant buffer the vertex shader uses to transform vertex
+    /// positions from points into normalized device coordinates.
+    fn write_transform(
+        &self,
+        frame_size: (u32, u32),
+        pixels_per_point: f32,
+        snap_to_pixels: bool,
     ) -> Result<()> {
-        let ib = Self::create_index_buffer(device, &mesh.idx)?;
-        let vb = Self::create_vertex_buffer(device, &mesh.vtx)?;
+        let transform = Transform {
+            ndc_from_pixels: [
+                2.0 / frame_size.0 as f32,
+                -2.0 / frame_size.1 as f32,
+                -1.0,
+                1.0,
+            ],
+            pixels_per_point,
+            snap_to_pixels: if snap_to_pixels { 1.0 } else { 0.0 },
+            _padding: [0.0; 2],
+            tint: self.output_tint().to_array(),
+        };
         unsafe {
-            device_context.IASetVertexBuffers(
+            let mut mapped = std::ptr::null_mut();
+            self.transform_buffer.Map(
+                D3D10_MAP_WRITE_DISCARD,
                 0,
-                1,
-                Some(&Some(vb.clone())),
-                Some(&(mem::size_of::<VertexData>() as _)),
-                Some(&0),
+                &mut mapped,
+            )?;
+            (mapped as *mut Transform).write(transform);
+            self.transform_buffer.Unmap();
+        }
+        Ok(())
+    }
+
+    /// The tint applied by the vertex shader, which includes the scale of
+    /// HDR output. Scaling the premultiplied color leaves alpha, and thus
+    /// blending, unchanged.
+    fn output_tint(&self) -> egui::Rgba {
+        // the nits of 1.0 in scRGB.
+        const SCRGB_WHITE_LEVEL: f32 = 80.0;
+        let Some(white_level) = self.hdr_white_level else {
+            return self.tint;
+        };
+        let scale = white_level / SCRGB_WHITE_LEVEL;
+        let [r, g, b, a] = self.tint.to_array();
+        egui::Rgba::from_rgba_premultiplied(r * scale, g * scale, b * scale, a)
+    }
+
+    /// Run a paint callback with the pipeline state of the renderer bound,
+    /// the viewport set to the rect of the callback and the scissor rect set
+    /// to its clip rect, then restore the state for the following meshes.
+    #[allow(clippy::too_many_arguments)]
+    fn call_paint_callback(
+        &mut self,
+        device_context: &ID3D10Device,
+        render_targets: &[ID3D10RenderTargetView],
+        target_resources: &[ID3D10Resource],
+        frame: Frame,
+        pixels_per_point: f32,
+        callback: &egui::PaintCallback,
+        clip_rect: egui::Rect,
+    ) -> Result<()> {
+        let Some(callback_fn) =
+            callback.callback.downcast_ref::<PaintCallbackFn>()
+        else {
+            warn!(
+                self.diagnostics,
+                concat!(
+                    "egui wants to run a paint callback that is not a ",
+                    "PaintCallbackFn. this request will be ignored."
+                )
             );
-            device_context.IASetIndexBuffer(&ib, DXGI_FORMAT_R32_UINT, 0);
-            device_context.RSSetScissorRects(Some(&[RECT {
-                left: mesh.clip_rect.left() as _,
-                top: mesh.clip_rect.top() as _,
-                right: mesh.clip_rect.right() as _,
-                bottom: mesh.clip_rect.bottom() as _,
+            if self.debug_unsupported_callbacks {
+                let mut mesh = egui::Mesh::default();
+                mesh.add_colored_rect(
+                    callback.rect,
+                    Color32::from_rgb(255, 0, 255),
+                );
+                self.draw_mesh(
+                    device_context,
+                    target_resources,
+                    frame,
+                    &PreparedMesh {
+                        mesh: &mesh,
+                        indices: &mesh.indices,
+                        clip_rect: clip_rect * pixels_per_point,
+                    },
+                )?;
+            }
+            return Ok(());
+        };
+        let info = egui::PaintCallbackInfo {
+            viewport: callback.rect,
+            clip_rect,
+            pixels_per_point,
+            screen_size_px: [frame.size.0, frame.size.1],
+        };
+        let viewport = info.viewport_in_pixels();
+        let clip = info.clip_rect_in_pixels();
+        if viewport.width_px <= 0
+            || viewport.height_px <= 0
+            || clip.width_px <= 0
+            || clip.height_px <= 0
+        {
+            return Ok(());
+        }
+        unsafe {
+            device_context.RSSetViewports(Some(&[D3D10_VIEWPORT {
+                TopLeftX: frame.offset.0 + viewport.left_px,
+                TopLeftY: frame.offset.1 + viewport.top_px,
+                Width: viewport.width_px as _,
+                Height: viewport.height_px as _,
+                MinDepth: 0.,
+                MaxDepth: 1.,
             }]));
+            device_context.RSSetScissorRects(Some(&[Self::offset_rect(
+                RECT {
+                    left: clip.left_px,
+                    top: clip.top_px,
+                    right: clip.left_px + clip.width_px,
+                    bottom: clip.top_px + clip.height_px,
+                },
+                frame,
+            )]));
         }
-        if let Some(srv) = texture_pool.get_srv(mesh.tex) {
-            unsafe {
-                device_context.PSSetShaderResources(0, Some(&[Some(srv)]))
-            };
-        } else {
-            log::warn!(
+        (callback_fn.f)(info, device_context);
+        self.stats.callback_count += 1;
+        self.setup(device_context, render_targets, frame);
+        Ok(())
+    }
+
+    fn draw_mesh(
+        &mut self,
+        device_context: &ID3D10Device,
+        target_resources: &[ID3D10Resource],
+        frame: Frame,
+        mesh: &PreparedMesh,
+    ) -> Result<bool> {
+        let scissor_rect = Self::offset_rect(
+            Self::scissor_rect(mesh.clip_rect, frame.size),
+            frame,
+        );
+        let (vb, ib, ib_format) =
+            self.write_buffers(std::slice::from_ref(mesh))?;
+        unsafe {
+            Self::bind_buffers(device_context, &vb, &ib, ib_format);
+            device_context.RSSetScissorRects(Some(&[scissor_rect]));
+        }
+        self.draw_range(device_context, target_resources, mesh, 0, 0)
+    }
+
+    /// Draw meshes that all cover the whole frame from a single vertex and
+    /// index buffer, setting the scissor rect only once. The meshes actually
+    /// drawn are added to `draw_list`, if any.
+    fn draw_batched(
+        &mut self,
+        device_context: &ID3D10Device,
+        target_resources: &[ID3D10Resource],
+        frame: Frame,
+        meshes: &[PreparedMesh],
+        mut draw_list: Option<&mut Vec<MeshData>>,
+    ) -> Result<()> {
+        if meshes.is_empty() {
+            return Ok(());
+        }
+        let (vb, ib, ib_format) = self.write_buffers(meshes)?;
+        unsafe {
+            Self::bind_buffers(device_context, &vb, &ib, ib_format);
+            device_context.RSSetScissorRects(Some(&[Self::offset_rect(
+                RECT {
+                    left: 0,
+                    top: 0,
+                    right: frame.size.0 as _,
+                    bottom: frame.size.1 as _,
+                },
+                frame,
+            )]));
+        }
+        let (mut start_index, mut base_vertex) = (0, 0);
+        for mesh in meshes {
+            let range = (start_index, base_vertex);
+            start_index += mesh.indices.len();
+            base_vertex += mesh.mesh.vertices.len();
+            if self.draw_range(
+                device_context,
+                target_resources,
+                mesh,
+                range.0,
+                range.1,
+            )? {
+                if let Some(draw_list) = draw_list.as_deref_mut() {
+                    draw_list.push(mesh.to_mesh_data());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw `mesh` from the bound vertex and index buffers, which hold its
+    /// indices from `start_index` on and its vertices from `base_vertex` on,
+    /// e.g. after other meshes written into the same buffers. Returns
+    /// `false` if the mesh must not be drawn, see `bind_texture`.
+    fn draw_range(
+        &mut self,
+        device_context: &ID3D10Device,
+        target_resources: &[ID3D10Resource],
+        mesh: &PreparedMesh,
+        start_index: usize,
+        base_vertex: usize,
+    ) -> Result<bool> {
+        if !Self::bind_texture(
+            device_context,
+            &self.texture_pool,
+            &self.sampler_state,
+            &self.diagnostics,
+            target_resources,
+            mesh.mesh.texture_id,
+        )? {
+            return Ok(false);
+        }
+        unsafe {
+            device_context.DrawIndexed(
+                mesh.indices.len() as _,
+                start_index as _,
+                base_vertex as _,
+            )
+        };
+        self.stats.count_mesh(mesh);
+        Ok(true)
+    }
+
+    /// Bind the texture with the provided id to shader resource slot 0, and
+    /// its own sampler or the sampler of the renderer to sampler slot 0.
+    /// Returns `false` if the mesh sampling it must not be drawn.
+    fn bind_texture(
+        device_context: &ID3D10Device,
+        texture_pool: &TexturePool,
+        default_sampler: &ID3D10SamplerState,
+        diagnostics: &Diagnostics,
+        target_resources: &[ID3D10Resource],
+        tid: egui::TextureId,
+    ) -> Result<bool> {
+        let Some(srv) = texture_pool.get_srv(tid) else {
+            warn!(
+                diagnostics,
                 concat!(
-                    "egui wants to sample a non-existing texture {:?}.",
+                    "egui wants to sample a non-existing texture {:?}. ",
                     "this request will be ignored."
                 ),
-                mesh.tex
+                tid
             );
+            return Ok(false);
         };
-        unsafe { device_context.DrawIndexed(mesh.idx.len() as _, 0, 0) };
-        Ok(())
+        if let egui::TextureId::User(_) = tid {
+            if target_resources.contains(&unsafe { srv.GetResource() }?) {
+                warn!(
+                    diagnostics,
+                    concat!(
+                        "egui wants to sample the render target through {:?}. ",
+                        "this request will be ignored."
+                    ),
+                    tid
+                );
+                return Ok(false);
+            }
+        }
+        let sampler = texture_pool
+            .get_sampler(tid)
+            .unwrap_or_else(|| default_sampler.clone());
+        unsafe {
+            device_context.PSSetShaderResources(0, Some(&[Some(srv)]));
+            device_context.PSSetSamplers(0, Some(&[Some(sampler)]));
+        }
+        Ok(true)
+    }
+
+    unsafe fn bind_buffers(
+        device_context: &ID3D10Device,
+        vb: &ID3D10Buffer,
+        ib: &ID3D10Buffer,
+        ib_format: DXGI_FORMAT,
+    ) {
+        device_context.IASetVertexBuffers(
+            0,
+            1,
+            Some(&Some(vb.clone())),
+            Some(&(mem::size_of::<VertexData>() as _)),
+            Some(&0),
+        );
+        device_context.IASetIndexBuffer(ib, ib_format, 0);
+    }
+
+    /// Convert a clip rect in physical pixels into a scissor rect, clamped to
+    /// the bounds of the frame.
+    ///
+    /// `egui` already intersects the clip rect of every shape with the ones
+    /// of its ancestors, e.g. nested scroll areas, so it is used as is. Its
+    /// edges are rounded to the nearest pixel as in `egui`'s reference
+    /// renderers, rather than truncated, which would cut off the right and
+    /// bottom edges of fractional rects.
+    fn scissor_rect(clip_rect: egui::Rect, frame_size: (u32, u32)) -> RECT {
+        let (width, height) = (frame_size.0 as i32, frame_size.1 as i32);
+        let clip_rect = egui::Rect::from_min_max(
+            clip_rect.min.round(),
+            clip_rect.max.round(),
+        );
+        RECT {
+            left: (clip_rect.left() as i32).clamp(0, width),
+            top: (clip_rect.top() as i32).clamp(0, height),
+            right: (clip_rect.right() as i32).clamp(0, width),
+            bottom: (clip_rect.bottom() as i32).clamp(0, height),
+        }
+    }
+
+    /// Move a rect relative to `frame` into the coordinates of the render
+    /// target.
+    fn offset_rect(rect: RECT, frame: Frame) -> RECT {
+        let (x, y) = frame.offset;
+        RECT {
+            left: rect.left + x,
+            top: rect.top + y,
+            right: rect.right + x,
+            bottom: rect.bottom + y,
+        }
+    }
+}
+
+impl Renderer {
+    /// Create a texture to render into instead of a swap chain, e.g. for
+    /// thumbnails or compositing, and a render target view of it to pass to
+    /// [`Renderer::render`].
+    ///
+    /// Pass `DXGI_FORMAT_R8G8B8A8_UNORM_SRGB` as `format` unless you have a
+    /// reason not to, as the renderer expects a render target that encodes
+    /// its output by itself. The texture can also be bound as a shader
+    /// resource, so a view of it can be sampled afterwards, e.g. through
+    /// [`Renderer::register_native_texture`] in a subsequent pass.
+    pub fn create_offscreen_target(
+        &self,
+        width: u32,
+        height: u32,
+        format: DXGI_FORMAT,
+    ) -> Result<(ID3D10Texture2D, ID3D10RenderTargetView)> {
+        let tex = unsafe {
+            self.device.CreateTexture2D(
+                &D3D10_TEXTURE2D_DESC {
+                    Width: width,
+                    Height: height,
+                    MipLevels: 1,
+                    ArraySize: 1,
+                    Format: format,
+                    SampleDesc: DXGI_SAMPLE_DESC {
+                        Count: 1,
+                        Quality: 0,
+                    },
+                    Usage: D3D10_USAGE_DEFAULT,
+                    BindFlags: (D3D10_BIND_RENDER_TARGET.0
+                        | D3D10_BIND_SHADER_RESOURCE.0)
+                        as _,
+                    CPUAccessFlags: 0,
+                    MiscFlags: 0,
+                },
+                None,
+            )
+        }
+        .map_err(Error::create("offscreen texture"))?;
+        let mut rtv = None;
+        unsafe {
+            self.device
+                .CreateRenderTargetView(&tex, None, Some(&mut rtv))
+        }
+        .map_err(Error::create("render target view"))?;
+        Ok((tex, rtv.unwrap()))
+    }
+
+    /// Describe a staging texture that can receive a copy of the provided
+    /// render target, i.e. with matching size and format,
+    /// `D3D10_USAGE_STAGING` and `D3D10_CPU_ACCESS_READ`.
+    ///
+    /// Create the staging texture once with this descriptor and reuse it with
+    /// [`Renderer::read_into`] to read back frames continuously without
+    /// allocating a texture each time. The staging texture has to be recreated
+    /// whenever the render target is resized.
+    pub fn readback_desc(
+        render_target: &ID3D10RenderTargetView,
+    ) -> Result<D3D10_TEXTURE2D_DESC> {
+        let tex = Self::get_render_target_texture(render_target)?;
+        let mut desc = self::zeroed();
+        unsafe { tex.GetDesc(&mut desc) };
+        Ok(D3D10_TEXTURE2D_DESC {
+            MipLevels: 1,
+            ArraySize: 1,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D10_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D10_CPU_ACCESS_READ.0 as _,
+            MiscFlags: 0,
+            ..desc
+        })
+    }
+
+    /// Copy the content of the provided render target into `staging` and read
+    /// it back into CPU memory.
+    ///
+    /// `staging` must be created from [`Renderer::readback_desc`] for the same
+    /// render target. The returned pixels are tightly packed rows of 4 bytes
+    /// per pixel in RGBA order, with the row padding of the mapped texture
+    /// stripped and the channels of BGRA render targets swapped. Render
+    /// targets of any other format than 8-bit RGBA or BGRA, e.g. float ones,
+    /// fail with [`Error::InvalidArgument`]. Multisampled render targets have
+    /// to be resolved into a single-sampled texture before readback.
+    pub fn read_into(
+        device_context: &ID3D10Device,
+        render_target: &ID3D10RenderTargetView,
+        staging: &ID3D10Texture2D,
+    ) -> Result<Vec<u8>> {
+        let tex = Self::get_render_target_texture(render_target)?;
+        let mut desc = self::zeroed();
+        unsafe { staging.GetDesc(&mut desc) };
+        let bgra = match desc.Format {
+            DXGI_FORMAT_R8G8B8A8_UNORM
+            | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+            | DXGI_FORMAT_R8G8B8A8_TYPELESS => false,
+            DXGI_FORMAT_B8G8R8A8_UNORM
+            | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+            | DXGI_FORMAT_B8G8R8A8_TYPELESS => true,
+            format => {
+                return Err(Error::InvalidArgument(format!(
+                    "cannot read back a render target of format {:?}",
+                    format
+                )))
+            },
+        };
+        unsafe { device_context.CopyResource(staging, &tex) };
+
+        let mapped = unsafe { staging.Map(0, D3D10_MAP_READ, 0) }?;
+        let row_size = desc.Width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_size * desc.Height as usize);
+        for y in 0..desc.Height as usize {
+            let row = unsafe {
+                std::slice::from_raw_parts(
+                    (mapped.pData as *const u8)
+                        .add(y * mapped.RowPitch as usize),
+                    row_size,
+                )
+            };
+            pixels.extend_from_slice(row);
+        }
+        unsafe { staging.Unmap(0) };
+        if bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Ok(pixels)
+    }
+
+    /// Read back the content of the provided render target, e.g. to save a
+    /// screenshot or compare a frame against a reference image. Returns its
+    /// width, height and pixels as described for [`Renderer::read_into`].
+    ///
+    /// This creates a staging texture on every call. Create one with
+    /// [`Renderer::readback_desc`] and use [`Renderer::read_into`] instead to
+    /// read back many frames.
+    pub fn read_pixels(
+        device_context: &ID3D10Device,
+        render_target: &ID3D10RenderTargetView,
+    ) -> Result<(u32, u32, Vec<u8>)> {
+        let desc = Self::readback_desc(render_target)?;
+        let staging = unsafe { device_context.CreateTexture2D(&desc, None) }
+            .map_err(Error::create("staging texture"))?;
+        let pixels = Self::read_into(device_context, render_target, &staging)?;
+        Ok((desc.Width, desc.Height, pixels))
     }
 }
 
@@ -380,7 +2981,7 @@ impl Renderer {
         D3D10_INPUT_ELEMENT_DESC {
             SemanticName: windows::core::s!("COLOR"),
             SemanticIndex: 0,
-            Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
             InputSlot: 0,
             AlignedByteOffset: D3D10_APPEND_ALIGNED_ELEMENT,
             InputSlotClass: D3D10_INPUT_PER_VERTEX_DATA,
@@ -408,93 +3009,274 @@ impl Renderer {
         AddressW: D3D10_TEXTURE_ADDRESS_BORDER,
         ComparisonFunc: D3D10_COMPARISON_ALWAYS,
         BorderColor: [1., 1., 1., 1.],
+        MaxLOD: f32::MAX,
         ..self::zeroed()
     };
 
     const BLEND_DESC: D3D10_BLEND_DESC = D3D10_BLEND_DESC {
         AlphaToCoverageEnable: BOOL(0),
-        BlendEnable: [
-            BOOL(1),
-            BOOL(0),
-            BOOL(0),
-            BOOL(0),
-            BOOL(0),
-            BOOL(0),
-            BOOL(0),
-            BOOL(0),
-        ],
+        // the same for every render target, see `Renderer::render_to_targets`
+        BlendEnable: [BOOL(1); MAX_RENDER_TARGETS],
         SrcBlend: D3D10_BLEND_SRC_ALPHA,
         DestBlend: D3D10_BLEND_INV_SRC_ALPHA,
         BlendOp: D3D10_BLEND_OP_ADD,
         SrcBlendAlpha: D3D10_BLEND_ONE,
         DestBlendAlpha: D3D10_BLEND_ZERO,
         BlendOpAlpha: D3D10_BLEND_OP_ADD,
-        RenderTargetWriteMask: [
-            D3D10_COLOR_WRITE_ENABLE_ALL.0 as _,
-            self::zeroed(),
-            self::zeroed(),
-            self::zeroed(),
-            self::zeroed(),
-            self::zeroed(),
-            self::zeroed(),
-            self::zeroed(),
-        ],
+        RenderTargetWriteMask: [D3D10_COLOR_WRITE_ENABLE_ALL.0 as _;
+            MAX_RENDER_TARGETS],
     };
+
+    fn rasterizer_desc(
+        sample_count: u32,
+        custom: Option<D3D10_RASTERIZER_DESC>,
+    ) -> D3D10_RASTERIZER_DESC {
+        let desc = custom.unwrap_or(D3D10_RASTERIZER_DESC {
+            MultisampleEnable: BOOL::from(sample_count > 1),
+            ..Self::RASTERIZER_DESC
+        });
+        // clip rects rely on scissoring.
+        D3D10_RASTERIZER_DESC {
+            ScissorEnable: BOOL(1),
+            ..desc
+        }
+    }
+
+    fn sampler_desc(
+        mip_lod_bias: f32,
+        sampler_filter: SamplerFilter,
+        address_mode: AddressMode,
+        border_color: egui::Rgba,
+    ) -> D3D10_SAMPLER_DESC {
+        let address_mode = match address_mode {
+            AddressMode::Border => D3D10_TEXTURE_ADDRESS_BORDER,
+            AddressMode::Clamp => D3D10_TEXTURE_ADDRESS_CLAMP,
+            AddressMode::Wrap => D3D10_TEXTURE_ADDRESS_WRAP,
+        };
+        let (filter, max_anisotropy) = match sampler_filter {
+            SamplerFilter::Linear => (D3D10_FILTER_MIN_MAG_MIP_LINEAR, 0),
+            SamplerFilter::Nearest => (D3D10_FILTER_MIN_MAG_MIP_POINT, 0),
+            SamplerFilter::Anisotropic(max_anisotropy) => (
+                D3D10_FILTER_ANISOTROPIC,
+                max_anisotropy.clamp(1, D3D10_MAX_MAXANISOTROPY),
+            ),
+        };
+        D3D10_SAMPLER_DESC {
+            Filter: filter,
+            MaxAnisotropy: max_anisotropy,
+            AddressU: address_mode,
+            AddressV: address_mode,
+            AddressW: address_mode,
+            MipLODBias: mip_lod_bias,
+            BorderColor: border_color.to_array(),
+            ..Self::SAMPLER_DESC
+        }
+    }
+
+    fn blend_desc(
+        blend_mode: BlendMode,
+        alpha_to_coverage: bool,
+    ) -> D3D10_BLEND_DESC {
+        let desc = match blend_mode {
+            BlendMode::AlphaBlend => Self::BLEND_DESC,
+            BlendMode::PremultipliedAlpha => D3D10_BLEND_DESC {
+                SrcBlend: D3D10_BLEND_ONE,
+                DestBlend: D3D10_BLEND_INV_SRC_ALPHA,
+                SrcBlendAlpha: D3D10_BLEND_ONE,
+                DestBlendAlpha: D3D10_BLEND_INV_SRC_ALPHA,
+                ..Self::BLEND_DESC
+            },
+            BlendMode::Additive => D3D10_BLEND_DESC {
+                SrcBlend: D3D10_BLEND_ONE,
+                DestBlend: D3D10_BLEND_ONE,
+                SrcBlendAlpha: D3D10_BLEND_ZERO,
+                DestBlendAlpha: D3D10_BLEND_ONE,
+                ..Self::BLEND_DESC
+            },
+        };
+        D3D10_BLEND_DESC {
+            AlphaToCoverageEnable: BOOL::from(alpha_to_coverage),
+            ..desc
+        }
+    }
 }
 
 impl Renderer {
-    fn create_vertex_buffer(
-        device: &ID3D10Device,
-        data: &[VertexData],
+    /// Get a vertex and index buffer holding the geometry of `meshes` one
+    /// after another, from the geometry cache if it is enabled. Returns them
+    /// along with the format to bind the index buffer with.
+    fn write_buffers(
+        &mut self,
+        meshes: &[PreparedMesh],
+    ) -> Result<(ID3D10Buffer, ID3D10Buffer, DXGI_FORMAT)> {
+        if let Some(geometry_cache) = &mut self.geometry_cache {
+            return geometry_cache.get_or_create(
+                &self.device,
+                meshes,
+                self.debug_names,
+            );
+        }
+        let (ib, ib_format) = self.write_index_buffer(meshes)?;
+        let vb = self.write_vertex_buffer(meshes)?;
+        Ok((vb, ib, ib_format))
+    }
+
+    /// Write the vertices of `meshes` one after another into the vertex
+    /// buffer.
+    fn write_vertex_buffer(
+        &mut self,
+        meshes: &[PreparedMesh],
     ) -> Result<ID3D10Buffer> {
-        let mut vertex_buffer = None;
-        unsafe {
-            device.CreateBuffer(
-                &D3D10_BUFFER_DESC {
-                    ByteWidth: mem::size_of_val(data) as _,
-                    Usage: D3D10_USAGE_IMMUTABLE,
-                    BindFlags: D3D10_BIND_VERTEX_BUFFER.0 as _,
-                    ..D3D10_BUFFER_DESC::default()
-                },
-                Some(&D3D10_SUBRESOURCE_DATA {
-                    pSysMem: data.as_ptr() as _,
-                    ..D3D10_SUBRESOURCE_DATA::default()
-                }),
-                Some(&mut vertex_buffer),
-            )
-        }?;
-        Ok(vertex_buffer.unwrap())
+        Self::write_dynamic_buffer(
+            &self.device,
+            &mut self.vertex_buffer,
+            D3D10_BIND_VERTEX_BUFFER,
+            meshes.iter().map(|mesh| mesh.mesh.vertices.len()).sum(),
+            meshes.iter().flat_map(PreparedMesh::vertices),
+            self.debug_names.then_some("egui vertex buffer"),
+        )
+    }
+
+    /// Write the indices of `meshes` one after another into the index
+    /// buffer, as 16-bit indices if they all fit in them to halve the upload
+    /// size. Returns the buffer along with the format to bind it with.
+    fn write_index_buffer(
+        &mut self,
+        meshes: &[PreparedMesh],
+    ) -> Result<(ID3D10Buffer, DXGI_FORMAT)> {
+        let debug_name = self.debug_names.then_some("egui index buffer");
+        let len = meshes.iter().map(|mesh| mesh.indices.len()).sum();
+        let indices = || meshes.iter().flat_map(|mesh| mesh.indices);
+        if indices().all(|&index| index <= u16::MAX as u32) {
+            let buffer = Self::write_dynamic_buffer(
+                &self.device,
+                &mut self.index_buffer,
+                D3D10_BIND_INDEX_BUFFER,
+                len,
+                indices().map(|&index| index as u16),
+                debug_name,
+            )?;
+            Ok((buffer, DXGI_FORMAT_R16_UINT))
+        } else {
+            let buffer = Self::write_dynamic_buffer(
+                &self.device,
+                &mut self.index_buffer,
+                D3D10_BIND_INDEX_BUFFER,
+                len,
+                indices().copied(),
+                debug_name,
+            )?;
+            Ok((buffer, DXGI_FORMAT_R32_UINT))
+        }
     }
 
-    fn create_index_buffer(
+    /// Overwrite the content of a dynamic buffer with the `len` items of
+    /// `data`, written straight into the mapped buffer and discarding the
+    /// previous content. The buffer is only recreated, with a power of two
+    /// size, when the data does not fit into it. Fails without touching the
+    /// buffer if there is nothing to write, as D3D10 has no empty buffers.
+    fn write_dynamic_buffer<T: Copy>(
         device: &ID3D10Device,
-        data: &[u32],
+        buffer: &mut Option<ID3D10Buffer>,
+        bind_flag: D3D10_BIND_FLAG,
+        len: usize,
+        data: impl Iterator<Item = T>,
+        debug_name: Option<&str>,
     ) -> Result<ID3D10Buffer> {
-        let mut index_buffer = None;
+        if len == 0 {
+            return Err(Error::InvalidArgument(format!(
+                "cannot write an empty {}",
+                Self::buffer_name(bind_flag)
+            )));
+        }
+        let size = len * mem::size_of::<T>();
+        let capacity = buffer.as_ref().map_or(0, |buffer| {
+            let mut desc = self::zeroed();
+            unsafe { buffer.GetDesc(&mut desc) };
+            desc.ByteWidth as usize
+        });
+        if capacity < size {
+            let mut new_buffer = None;
+            unsafe {
+                device.CreateBuffer(
+                    &D3D10_BUFFER_DESC {
+                        ByteWidth: size.next_power_of_two() as _,
+                        Usage: D3D10_USAGE_DYNAMIC,
+                        BindFlags: bind_flag.0 as _,
+                        CPUAccessFlags: D3D10_CPU_ACCESS_WRITE.0 as _,
+                        ..D3D10_BUFFER_DESC::default()
+                    },
+                    None,
+                    Some(&mut new_buffer),
+                )
+            }
+            .map_err(Error::create(Self::buffer_name(bind_flag)))?;
+            let new_buffer = new_buffer.unwrap();
+            if let Some(name) = debug_name {
+                set_debug_name(&new_buffer, name)?;
+            }
+            *buffer = Some(new_buffer);
+        }
+        let buffer = buffer.as_ref().unwrap();
         unsafe {
-            device.CreateBuffer(
-                &D3D10_BUFFER_DESC {
-                    ByteWidth: mem::size_of_val(data) as _,
-                    Usage: D3D10_USAGE_IMMUTABLE,
-                    BindFlags: D3D10_BIND_INDEX_BUFFER.0 as _,
-                    ..D3D10_BUFFER_DESC::default()
-                },
-                Some(&D3D10_SUBRESOURCE_DATA {
-                    pSysMem: data.as_ptr() as _,
-                    ..D3D10_SUBRESOURCE_DATA::default()
-                }),
-                Some(&mut index_buffer),
-            )
-        }?;
-        Ok(index_buffer.unwrap())
+            let mut mapped = std::ptr::null_mut();
+            buffer.Map(D3D10_MAP_WRITE_DISCARD, 0, &mut mapped)?;
+            let mapped = mapped as *mut T;
+            for (i, item) in data.take(len).enumerate() {
+                mapped.add(i).write(item);
+            }
+            buffer.Unmap();
+        }
+        Ok(buffer.clone())
+    }
+
+    fn buffer_name(bind_flag: D3D10_BIND_FLAG) -> &'static str {
+        if bind_flag == D3D10_BIND_VERTEX_BUFFER {
+            "vertex buffer"
+        } else {
+            "index buffer"
+        }
     }
 
-    fn get_render_target_size(
+    fn get_render_target_texture(
         rtv: &ID3D10RenderTargetView,
-    ) -> Result<(u32, u32)> {
-        let tex = unsafe { rtv.GetResource() }?.cast::<ID3D10Texture2D>()?;
+    ) -> Result<ID3D10Texture2D> {
+        Ok(unsafe { rtv.GetResource() }?.cast::<ID3D10Texture2D>()?)
+    }
+
+    /// Warn once if the render target stores the linear output of the pixel
+    /// shader as is, which makes colors look too dark.
+    fn check_render_target_format(&mut self, rtv: &ID3D10RenderTargetView) {
+        if self.gamma_output
+            || self.hdr_white_level.is_some()
+            || self.warned_render_target_format
+        {
+            return;
+        }
         let mut desc = self::zeroed();
-        unsafe { tex.GetDesc(&mut desc) };
-        Ok((desc.Width, desc.Height))
+        unsafe { rtv.GetDesc(&mut desc) };
+        // sRGB formats encode the output and float formats are meant to hold
+        // linear color. any other format, e.g. the common swap chain format
+        // `DXGI_FORMAT_R10G10B10A2_UNORM`, stores it as is.
+        if !matches!(
+            desc.Format,
+            DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+                | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+                | DXGI_FORMAT_B8G8R8X8_UNORM_SRGB
+                | DXGI_FORMAT_R32G32B32A32_FLOAT
+                | DXGI_FORMAT_R32G32B32_FLOAT
+                | DXGI_FORMAT_R16G16B16A16_FLOAT
+                | DXGI_FORMAT_R11G11B10_FLOAT
+        ) {
+            warn!(
+                self.diagnostics,
+                concat!(
+                    "the render target format {:?} is not sRGB and gamma ",
+                    "output is disabled. colors will look wrong."
+                ),
+                desc.Format
+            );
+            self.warned_render_target_format = true;
+        }
     }
 }
@@ -0,0 +1,230 @@
+use windows::Win32::{
+    Foundation::RECT,
+    Graphics::{Direct3D::*, Direct3D10::*, Dxgi::Common::*},
+};
+
+/// A snapshot of the Direct3D10 pipeline state of a device, which can be
+/// applied again later.
+///
+/// [`Renderer::render`](crate::Renderer::render) overrides various pipeline
+/// states without restoring them. Capture a [`StateBlock`] before rendering
+/// and apply it afterwards to get your own pipeline state back. This works
+/// just as well around any other draw calls of your own.
+///
+/// The snapshot covers:
+/// + The input layout, vertex buffer slot 0, index buffer and primitive
+///   topology in the input assembly stage;
+/// + The current shader and constant buffer slot 0 in the vertex shader
+///   stage;
+/// + The viewports, scissor rects and rasterizer state in the rasterizer
+///   stage;
+/// + The current shader, shader resource slot 0 and sampler slot 0 in the
+///   pixel shader stage;
+/// + The render targets, depth stencil view, blend state and depth stencil
+///   state in the output merger stage.
+///
+/// Any other state, e.g. other shader resource slots or the geometry shader
+/// stage, is neither captured nor applied.
+///
+/// The snapshot holds references to the captured resources and states, so
+/// they stay alive until it is dropped.
+pub struct StateBlock {
+    primitive_topology: D3D_PRIMITIVE_TOPOLOGY,
+    input_layout: Option<ID3D10InputLayout>,
+    vertex_buffer: Option<ID3D10Buffer>,
+    vertex_stride: u32,
+    vertex_offset: u32,
+    index_buffer: Option<ID3D10Buffer>,
+    index_format: DXGI_FORMAT,
+    index_offset: u32,
+
+    vertex_shader: Option<ID3D10VertexShader>,
+    constant_buffer: Option<ID3D10Buffer>,
+
+    rasterizer_state: Option<ID3D10RasterizerState>,
+    viewports: Vec<D3D10_VIEWPORT>,
+    scissor_rects: Vec<RECT>,
+
+    pixel_shader: Option<ID3D10PixelShader>,
+    shader_resource: Option<ID3D10ShaderResourceView>,
+    sampler: Option<ID3D10SamplerState>,
+
+    render_targets: [Option<ID3D10RenderTargetView>;
+        D3D10_SIMULTANEOUS_RENDER_TARGET_COUNT as usize],
+    depth_stencil_view: Option<ID3D10DepthStencilView>,
+    blend_state: Option<ID3D10BlendState>,
+    blend_factor: [f32; 4],
+    sample_mask: u32,
+    depth_stencil_state: Option<ID3D10DepthStencilState>,
+    stencil_ref: u32,
+}
+
+impl StateBlock {
+    /// Capture the current pipeline state of `device`.
+    pub fn capture(device: &ID3D10Device) -> Self {
+        let mut state = Self {
+            primitive_topology: D3D_PRIMITIVE_TOPOLOGY_UNDEFINED,
+            input_layout: None,
+            vertex_buffer: None,
+            vertex_stride: 0,
+            vertex_offset: 0,
+            index_buffer: None,
+            index_format: DXGI_FORMAT_UNKNOWN,
+            index_offset: 0,
+            vertex_shader: None,
+            constant_buffer: None,
+            rasterizer_state: None,
+            viewports: Vec::new(),
+            scissor_rects: Vec::new(),
+            pixel_shader: None,
+            shader_resource: None,
+            sampler: None,
+            render_targets: Default::default(),
+            depth_stencil_view: None,
+            blend_state: None,
+            blend_factor: [0.; 4],
+            sample_mask: 0,
+            depth_stencil_state: None,
+            stencil_ref: 0,
+        };
+        unsafe {
+            state.primitive_topology = device.IAGetPrimitiveTopology();
+            state.input_layout = device.IAGetInputLayout().ok();
+            device.IAGetVertexBuffers(
+                0,
+                1,
+                Some(&mut state.vertex_buffer),
+                Some(&mut state.vertex_stride),
+                Some(&mut state.vertex_offset),
+            );
+            device.IAGetIndexBuffer(
+                Some(&mut state.index_buffer),
+                Some(&mut state.index_format),
+                Some(&mut state.index_offset),
+            );
+
+            state.vertex_shader = device.VSGetShader().ok();
+            let mut constant_buffers = [None];
+            device.VSGetConstantBuffers(0, Some(&mut constant_buffers));
+            [state.constant_buffer] = constant_buffers;
+
+            state.rasterizer_state = device.RSGetState().ok();
+            let mut count = 0;
+            device.RSGetViewports(&mut count, None);
+            state.viewports = vec![crate::zeroed(); count as usize];
+            device
+                .RSGetViewports(&mut count, Some(state.viewports.as_mut_ptr()));
+            let mut count = 0;
+            device.RSGetScissorRects(&mut count, None);
+            state.scissor_rects = vec![RECT::default(); count as usize];
+            device.RSGetScissorRects(
+                &mut count,
+                Some(state.scissor_rects.as_mut_ptr()),
+            );
+
+            state.pixel_shader = device.PSGetShader().ok();
+            let mut shader_resources = [None];
+            device.PSGetShaderResources(0, Some(&mut shader_resources));
+            [state.shader_resource] = shader_resources;
+            let mut samplers = [None];
+            device.PSGetSamplers(0, Some(&mut samplers));
+            [state.sampler] = samplers;
+
+            device.OMGetRenderTargets(
+                Some(&mut state.render_targets),
+                Some(&mut state.depth_stencil_view),
+            );
+            device.OMGetBlendState(
+                Some(&mut state.blend_state),
+                Some(&mut state.blend_factor),
+                Some(&mut state.sample_mask),
+            );
+            device.OMGetDepthStencilState(
+                Some(&mut state.depth_stencil_state),
+                Some(&mut state.stencil_ref),
+            );
+        }
+        state
+    }
+
+    /// Apply the captured pipeline state to `device`. The same snapshot can be
+    /// applied any number of times.
+    pub fn apply(&self, device: &ID3D10Device) {
+        unsafe {
+            device.IASetPrimitiveTopology(self.primitive_topology);
+            device.IASetInputLayout(self.input_layout.as_ref());
+            device.IASetVertexBuffers(
+                0,
+                1,
+                Some(&self.vertex_buffer),
+                Some(&self.vertex_stride),
+                Some(&self.vertex_offset),
+            );
+            device.IASetIndexBuffer(
+                self.index_buffer.as_ref(),
+                self.index_format,
+                self.index_offset,
+            );
+
+            device.VSSetShader(self.vertex_shader.as_ref());
+            device.VSSetConstantBuffers(
+                0,
+                Some(std::slice::from_ref(&self.constant_buffer)),
+            );
+
+            device.RSSetState(self.rasterizer_state.as_ref());
+            device.RSSetViewports(Some(&self.viewports));
+            device.RSSetScissorRects(Some(&self.scissor_rects));
+
+            device.PSSetShader(self.pixel_shader.as_ref());
+            device.PSSetShaderResources(
+                0,
+                Some(std::slice::from_ref(&self.shader_resource)),
+            );
+            device.PSSetSamplers(0, Some(std::slice::from_ref(&self.sampler)));
+
+            device.OMSetRenderTargets(
+                Some(&self.render_targets),
+                self.depth_stencil_view.as_ref(),
+            );
+            device.OMSetBlendState(
+                self.blend_state.as_ref(),
+                &self.blend_factor,
+                self.sample_mask,
+            );
+            device.OMSetDepthStencilState(
+                self.depth_stencil_state.as_ref(),
+                self.stencil_ref,
+            );
+        }
+    }
+}
+
+/// Captures the pipeline state of a device when created and applies it again
+/// when dropped, see [`StateBlock`].
+///
+/// This restores the state on every path out of a scope, including early
+/// returns with `?`, e.g.
+/// `let _guard = renderer.state_guard(&device);` before rendering. It borrows
+/// only the device, so the [`Renderer`](crate::Renderer) can still render
+/// while the guard is alive.
+pub struct StateGuard<'a> {
+    device: &'a ID3D10Device,
+    state: StateBlock,
+}
+
+impl<'a> StateGuard<'a> {
+    /// Capture the current pipeline state of `device`.
+    pub fn new(device: &'a ID3D10Device) -> Self {
+        Self {
+            device,
+            state: StateBlock::capture(device),
+        }
+    }
+}
+
+impl Drop for StateGuard<'_> {
+    fn drop(&mut self) {
+        self.state.apply(self.device);
+    }
+}
@@ -8,25 +8,106 @@
 //
 // Nekomaru, March 2024
 
-use std::{collections::HashMap, mem};
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    mem,
+    sync::Arc,
+};
 
 use egui::{Color32, ImageData, TextureId, TexturesDelta};
 
+use crate::{Diagnostics, Error, Result, SamplerFilter};
+
 use windows::{
-    core::Result,
-    Win32::Graphics::{Direct3D10::*, Dxgi::Common::*},
+    core::Interface,
+    Win32::Graphics::{
+        Direct3D::*, Direct3D10::*, Dxgi::Common::*, Dxgi::IDXGIKeyedMutex,
+    },
 };
 
+/// The key shared textures are acquired and released with, see
+/// [`Renderer::register_shared_texture`](crate::Renderer::register_shared_texture).
+const SHARED_TEXTURE_KEY: u64 = 0;
+
 struct Texture {
     tex: ID3D10Texture2D,
     srv: ID3D10ShaderResourceView,
-    pixels: Vec<Color32>,
+    /// CPU mirror of the texture, only kept when mirroring is enabled.
+    pixels: Option<Vec<Color32>>,
     width: usize,
+    height: usize,
+    kind: TextureKind,
+}
+
+/// How a texture is created, which also decides how partial updates are
+/// written, see `update_partial` and `flush_mirror`.
+#[derive(Clone, Copy)]
+struct TextureKind {
+    usage: D3D10_USAGE,
+    /// Whether a CPU mirror of the pixels is kept.
+    mirror: bool,
+    /// Whether the texture has a full mip chain, generated on the GPU.
+    mipmaps: bool,
+}
+
+struct NativeTexture {
+    srv: ID3D10ShaderResourceView,
+    /// Estimated at 4 bytes per pixel of the top mip level.
+    bytes: usize,
+    /// The frame the texture was last sampled in, see `get_srv`.
+    last_used: Cell<u64>,
+    /// Held around the draw calls sampling a texture shared with another
+    /// device, see `acquire_shared`.
+    keyed_mutex: Option<IDXGIKeyedMutex>,
+    /// Bound instead of the sampler of the renderer when the texture is
+    /// sampled, along with the filter it was created with.
+    sampler: Option<(SamplerFilter, ID3D10SamplerState)>,
+}
+
+/// Memory taken by the textures uploaded by `egui`, as reported by
+/// [`Renderer::texture_memory_usage`](crate::Renderer::texture_memory_usage).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureMemoryUsage {
+    /// Number of textures.
+    pub texture_count: usize,
+    /// Bytes of texture data on the GPU, at 4 bytes per pixel of every mip
+    /// level.
+    pub gpu_bytes: usize,
+    /// Bytes taken by the CPU mirrors of the textures. See
+    /// [`Renderer::set_texture_mirror`](crate::Renderer::set_texture_mirror).
+    pub mirror_bytes: usize,
+}
+
+/// How many textures the texture delta of a frame touched, as reported in
+/// [`RenderStats::textures`](crate::RenderStats::textures).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureUpdateStats {
+    /// Number of textures created or replaced as a whole, e.g. when the font
+    /// atlas is rebuilt.
+    pub created: usize,
+    /// Number of partial updates of existing textures.
+    pub updated: usize,
+    /// Number of textures freed.
+    pub freed: usize,
 }
 
 pub struct TexturePool {
     device: ID3D10Device,
     pool: HashMap<TextureId, Texture>,
+    mirror: bool,
+    srgb: bool,
+    mipmaps: bool,
+    premultiply: bool,
+    debug_names: bool,
+    native: HashMap<u64, NativeTexture>,
+    /// Ids of native textures dropped by `rebuild_on_device`, kept until
+    /// they are supplied again with `update_native` or freed.
+    lost_native: HashSet<u64>,
+    next_native_id: u64,
+    budget: Option<usize>,
+    frame: u64,
+    diagnostics: Diagnostics,
 }
 
 impl TexturePool {
@@ -34,112 +115,726 @@ impl TexturePool {
         Self {
             device: device.clone(),
             pool: HashMap::new(),
+            mirror: true,
+            srgb: true,
+            mipmaps: false,
+            premultiply: false,
+            debug_names: false,
+            native: HashMap::new(),
+            lost_native: HashSet::new(),
+            next_native_id: 0,
+            budget: None,
+            frame: 0,
+            diagnostics: Diagnostics::default(),
+        }
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Limit the memory taken by native textures. Once exceeded, the least
+    /// recently sampled ones are evicted by `update`.
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+
+    pub fn mirror(&self) -> bool {
+        self.mirror
+    }
+
+    /// Enable or disable the CPU mirror for textures created from now on.
+    /// Disabling it also drops the mirrors of all existing textures, which
+    /// are recreated with `D3D10_USAGE_DEFAULT` for that unless they already
+    /// use it.
+    pub fn set_mirror(&mut self, mirror: bool) -> Result<()> {
+        if !mirror {
+            for (&tid, tex) in self.pool.iter_mut() {
+                let Some(pixels) = tex.pixels.take() else {
+                    continue;
+                };
+                tex.kind.mirror = false;
+                if tex.kind.usage == D3D10_USAGE_DEFAULT {
+                    continue;
+                }
+                *tex = Self::create_texture_from_pixels(
+                    &self.device,
+                    pixels,
+                    tex.width,
+                    tex.height,
+                    TextureKind {
+                        usage: D3D10_USAGE_DEFAULT,
+                        ..tex.kind
+                    },
+                    self.srgb,
+                )?;
+                if self.debug_names {
+                    Self::set_debug_name(tid, tex)?;
+                }
+            }
+        }
+        self.mirror = mirror;
+        Ok(())
+    }
+
+    pub fn mipmaps(&self) -> bool {
+        self.mipmaps
+    }
+
+    /// Create color textures with a full mip chain from now on.
+    pub fn set_mipmaps(&mut self, mipmaps: bool) {
+        self.mipmaps = mipmaps;
+    }
+
+    pub fn premultiply(&self) -> bool {
+        self.premultiply
+    }
+
+    /// Multiply the color of the pixels by their alpha when uploading
+    /// textures from now on.
+    pub fn set_premultiply(&mut self, premultiply: bool) {
+        self.premultiply = premultiply;
+    }
+
+    pub fn srgb(&self) -> bool {
+        self.srgb
+    }
+
+    /// Select whether textures are sampled as sRGB or as linear data. The
+    /// views of all existing textures are recreated for that.
+    pub fn set_srgb(&mut self, srgb: bool) -> Result<()> {
+        if srgb != self.srgb {
+            for (&tid, tex) in self.pool.iter_mut() {
+                tex.srv = Self::create_srv(&self.device, &tex.tex, srgb)?;
+                if self.debug_names {
+                    Self::set_debug_name(tid, tex)?;
+                }
+            }
+            self.srgb = srgb;
+        }
+        Ok(())
+    }
+
+    /// Name textures created from now on for graphics debuggers, and name the
+    /// existing ones too if enabled.
+    pub fn set_debug_names(&mut self, debug_names: bool) -> Result<()> {
+        self.debug_names = debug_names;
+        if debug_names {
+            for (&tid, tex) in &self.pool {
+                Self::set_debug_name(tid, tex)?;
+            }
+            for (_, sampler) in
+                self.native.values().filter_map(|t| t.sampler.as_ref())
+            {
+                crate::set_debug_name(sampler, "egui sampler state")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_debug_name(tid: TextureId, tex: &Texture) -> Result<()> {
+        let name = match tid {
+            TextureId::Managed(0) => "egui font atlas".to_owned(),
+            TextureId::Managed(id) | TextureId::User(id) => {
+                format!("egui texture {id}")
+            },
+        };
+        crate::set_debug_name(&tex.tex, &name)?;
+        crate::set_debug_name(&tex.srv, &name)
+    }
+
+    pub fn memory_usage(&self) -> TextureMemoryUsage {
+        let mut usage = TextureMemoryUsage {
+            texture_count: self.pool.len(),
+            ..Default::default()
+        };
+        for tex in self.pool.values() {
+            let (mut width, mut height) = (tex.width, tex.height);
+            usage.gpu_bytes += width * height * mem::size_of::<Color32>();
+            while tex.kind.mipmaps && (width > 1 || height > 1) {
+                width = (width / 2).max(1);
+                height = (height / 2).max(1);
+                usage.gpu_bytes += width * height * mem::size_of::<Color32>();
+            }
+            if let Some(pixels) = &tex.pixels {
+                usage.mirror_bytes += mem::size_of_val(pixels.as_slice());
+            }
         }
+        usage
     }
 
     pub fn get_srv(&self, tid: TextureId) -> Option<ID3D10ShaderResourceView> {
-        self.pool.get(&tid).map(|t| t.srv.clone())
+        match tid {
+            TextureId::Managed(_) => self.pool.get(&tid).map(|t| t.srv.clone()),
+            TextureId::User(id) => self.native.get(&id).map(|t| {
+                t.last_used.set(self.frame);
+                t.srv.clone()
+            }),
+        }
+    }
+
+    /// Get the sampler overriding the one of the renderer for a native
+    /// texture, see `set_native_sampler`.
+    pub fn get_sampler(&self, tid: TextureId) -> Option<ID3D10SamplerState> {
+        match tid {
+            TextureId::Managed(_) => None,
+            TextureId::User(id) => self
+                .native
+                .get(&id)
+                .and_then(|t| t.sampler.as_ref())
+                .map(|(_, sampler)| sampler.clone()),
+        }
+    }
+
+    /// Set or clear the sampler of a native texture. Returns `false` if
+    /// `tid` is not a native texture.
+    pub fn set_native_sampler(
+        &mut self,
+        tid: TextureId,
+        sampler: Option<(SamplerFilter, ID3D10SamplerState)>,
+    ) -> bool {
+        let TextureId::User(id) = tid else {
+            return false;
+        };
+        match self.native.get_mut(&id) {
+            Some(tex) => {
+                tex.sampler = sampler;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The native textures with a sampler of their own, along with the
+    /// filter of the sampler.
+    pub fn native_sampler_filters(&self) -> Vec<(TextureId, SamplerFilter)> {
+        self.native
+            .iter()
+            .filter_map(|(&id, t)| {
+                t.sampler
+                    .as_ref()
+                    .map(|&(filter, _)| (TextureId::User(id), filter))
+            })
+            .collect()
+    }
+
+    /// Register a texture created outside of egui. It is stored apart from
+    /// the egui-managed textures, so `update` never touches it.
+    pub fn register_native(
+        &mut self,
+        srv: ID3D10ShaderResourceView,
+        keyed_mutex: Option<IDXGIKeyedMutex>,
+    ) -> TextureId {
+        let id = self.next_native_id;
+        self.next_native_id += 1;
+        self.native.insert(id, self.new_native(srv, keyed_mutex));
+        TextureId::User(id)
+    }
+
+    fn new_native(
+        &self,
+        srv: ID3D10ShaderResourceView,
+        keyed_mutex: Option<IDXGIKeyedMutex>,
+    ) -> NativeTexture {
+        NativeTexture {
+            bytes: Self::native_bytes(&srv),
+            srv,
+            last_used: Cell::new(self.frame),
+            keyed_mutex,
+            sampler: None,
+        }
+    }
+
+    /// Acquire the keyed mutexes of the shared textures among `tids`, each
+    /// only once, blocking until the other device releases them. They must
+    /// be passed to `release_shared` once the draw calls sampling the
+    /// textures are issued.
+    pub fn acquire_shared(
+        &self,
+        tids: impl IntoIterator<Item = TextureId>,
+    ) -> Result<Vec<IDXGIKeyedMutex>> {
+        let mut acquired = Vec::<IDXGIKeyedMutex>::new();
+        for tid in tids {
+            let TextureId::User(id) = tid else {
+                continue;
+            };
+            let Some(keyed_mutex) =
+                self.native.get(&id).and_then(|t| t.keyed_mutex.as_ref())
+            else {
+                continue;
+            };
+            if acquired.contains(keyed_mutex) {
+                continue;
+            }
+            // INFINITE, as a timeout would be reported as a success code.
+            if let Err(err) =
+                unsafe { keyed_mutex.AcquireSync(SHARED_TEXTURE_KEY, u32::MAX) }
+            {
+                Self::release_shared(&acquired);
+                return Err(err.into());
+            }
+            acquired.push(keyed_mutex.clone());
+        }
+        Ok(acquired)
+    }
+
+    pub fn release_shared(acquired: &[IDXGIKeyedMutex]) {
+        for keyed_mutex in acquired {
+            // nothing sensible to do if this fails, the other device will
+            // time out or fail to acquire it in turn.
+            let _ = unsafe { keyed_mutex.ReleaseSync(SHARED_TEXTURE_KEY) };
+        }
+    }
+
+    /// Replace the view of a native texture, or supply the view of a native
+    /// texture lost by `rebuild_on_device` again under its previous id.
+    pub fn update_native(
+        &mut self,
+        tid: TextureId,
+        srv: ID3D10ShaderResourceView,
+    ) -> bool {
+        let TextureId::User(id) = tid else {
+            return false;
+        };
+        match self.native.get_mut(&id) {
+            Some(old) => {
+                old.bytes = Self::native_bytes(&srv);
+                old.srv = srv;
+                // the mutex belongs to the texture of the previous view.
+                old.keyed_mutex = None;
+                true
+            },
+            None if self.lost_native.remove(&id) => {
+                self.native.insert(id, self.new_native(srv, None));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Drop every texture, including the native ones. Ids of native textures
+    /// are not reused afterwards.
+    pub fn clear(&mut self) {
+        self.pool.clear();
+        self.native.clear();
+        self.lost_native.clear();
     }
 
+    pub fn free_native(&mut self, tid: TextureId) {
+        if let TextureId::User(id) = tid {
+            self.native.remove(&id);
+            self.lost_native.remove(&id);
+        }
+    }
+
+    /// Estimate the memory behind a native texture at 4 bytes per pixel of
+    /// the top mip level, whatever its format. Views of anything but a 2D
+    /// texture count as nothing.
+    fn native_bytes(srv: &ID3D10ShaderResourceView) -> usize {
+        let Ok(tex) = unsafe { srv.GetResource() }
+            .and_then(|res| res.cast::<ID3D10Texture2D>())
+        else {
+            return 0;
+        };
+        let mut desc = crate::zeroed();
+        unsafe { tex.GetDesc(&mut desc) };
+        desc.Width as usize
+            * desc.Height as usize
+            * desc.ArraySize as usize
+            * mem::size_of::<Color32>()
+    }
+
+    /// Evict the least recently sampled native textures until they fit into
+    /// the budget again.
+    fn evict_native(&mut self) {
+        let Some(budget) = self.budget else {
+            return;
+        };
+        let mut total = self.native.values().map(|t| t.bytes).sum::<usize>();
+        if total <= budget {
+            return;
+        }
+        let mut lru = self
+            .native
+            .iter()
+            .map(|(&id, t)| (t.last_used.get(), id))
+            .collect::<Vec<_>>();
+        lru.sort_unstable();
+        for (_, id) in lru {
+            if total <= budget {
+                break;
+            }
+            let tex = self.native.remove(&id).unwrap();
+            total -= tex.bytes;
+            #[cfg(feature = "logging")]
+            log::debug!("evicted native texture {:?}.", TextureId::User(id));
+        }
+    }
+
+    /// Apply a frame's texture delta.
+    ///
+    /// Partial updates, like egui growing its font atlas glyph by glyph, are
+    /// written into the existing GPU texture, so its resource and SRV stay
+    /// the same across frames. Only a whole update, like the atlas being
+    /// rebuilt after a font change, creates a new texture and drops the old
+    /// one. The exception are color images with a CPU mirror and without
+    /// mipmaps: they are immutable, as egui rarely updates them partially,
+    /// and are recreated from the mirror if it does.
+    ///
+    /// Native textures over the budget are evicted afterwards, and the frame
+    /// counter `get_srv` tracks their use with advances.
     pub fn update(
         &mut self,
         ctx: &ID3D10Device,
         delta: TexturesDelta,
-    ) -> Result<()> {
+    ) -> Result<TextureUpdateStats> {
+        let mut stats = TextureUpdateStats::default();
+        // the rects of the mirrors written by partial updates, which are
+        // written to the GPU once all updates are applied.
+        let mut dirty = HashMap::<TextureId, D3D10_BOX>::new();
         for (tid, delta) in delta.set {
             if delta.is_whole()
                 && delta.image.width() > 0
                 && delta.image.height() > 0
             {
-                self.pool.insert(
-                    tid,
-                    Self::create_texture(&self.device, delta.image)?,
-                );
+                // mipmaps are generated into the texture, which rules out
+                // both other usages.
+                let mipmaps =
+                    self.mipmaps && matches!(delta.image, ImageData::Color(_));
+                let usage = match (&delta.image, self.mirror) {
+                    _ if mipmaps => D3D10_USAGE_DEFAULT,
+                    (_, false) => D3D10_USAGE_DEFAULT,
+                    (ImageData::Font(_), true) => D3D10_USAGE_DYNAMIC,
+                    (ImageData::Color(_), true) => D3D10_USAGE_IMMUTABLE,
+                };
+                let kind = TextureKind {
+                    usage,
+                    mirror: self.mirror,
+                    mipmaps,
+                };
+                let tex = Self::create_texture(
+                    &self.device,
+                    delta.image,
+                    kind,
+                    self.srgb,
+                    self.premultiply,
+                )?;
+                if self.debug_names {
+                    Self::set_debug_name(tid, &tex)?;
+                }
+                self.pool.insert(tid, tex);
+                dirty.remove(&tid);
                 // the old texture is returned and dropped here, freeing
                 // all its gpu resource.
+                stats.created += 1;
             } else if let Some(tex) = self.pool.get_mut(&tid) {
-                Self::update_partial(
+                let (recreated, rect) = Self::update_partial(
+                    &self.device,
                     ctx,
+                    self.srgb,
+                    self.premultiply,
                     tex,
                     delta.image,
                     delta.pos.unwrap(),
                 )?;
+                if recreated && self.debug_names {
+                    Self::set_debug_name(tid, tex)?;
+                }
+                if let Some(rect) = rect {
+                    let dirty = dirty.entry(tid).or_insert(rect);
+                    dirty.left = dirty.left.min(rect.left);
+                    dirty.top = dirty.top.min(rect.top);
+                    dirty.right = dirty.right.max(rect.right);
+                    dirty.bottom = dirty.bottom.max(rect.bottom);
+                }
+                stats.updated += 1;
             } else {
-                log::warn!("egui wants to update a non-existing texture {tid:?}. this request will be ignored.");
+                warn!(self.diagnostics, "egui wants to update a non-existing texture {tid:?}. this request will be ignored.");
+            }
+        }
+        for (tid, rect) in dirty {
+            let tex = self.pool.get_mut(&tid).unwrap();
+            let recreated =
+                Self::flush_mirror(&self.device, ctx, self.srgb, tex, rect)?;
+            if recreated && self.debug_names {
+                Self::set_debug_name(tid, tex)?;
             }
         }
         for tid in delta.free {
-            self.pool.remove(&tid);
+            if self.pool.remove(&tid).is_some() {
+                stats.freed += 1;
+            }
         }
-        Ok(())
+        self.evict_native();
+        self.frame += 1;
+        Ok(stats)
     }
 
+    /// Write a sub-rectangle of a texture. Returns whether the texture had to
+    /// be recreated for that, and the rectangle left to be written to the
+    /// GPU with `flush_mirror`, if any.
+    ///
+    /// With a CPU mirror, only the mirror is written, so that it stays
+    /// authoritative: all updates of a delta land in the mirror in order, and
+    /// the GPU is written once from the final state, see `flush_mirror`.
+    /// Without a mirror, the texture is updated in place with
+    /// `UpdateSubresource`, and its mipmaps are generated again. A rectangle
+    /// reaching past the texture grows it first, see `resize`.
     fn update_partial(
+        device: &ID3D10Device,
         ctx: &ID3D10Device,
+        srgb: bool,
+        premultiply: bool,
         old: &mut Texture,
         image: ImageData,
         [nx, ny]: [usize; 2],
-    ) -> Result<()> {
-        match image {
-            ImageData::Font(f) => {
-                let row_pitch = old.width * 4; // 4 bytes per pixel
-                let mut update_data = vec![0u8; f.height() * row_pitch];
-
-                for y in 0..f.height() {
-                    for x in 0..f.width() {
-                        let frac = y * f.width() + x;
-                        let whole = (ny + y) * old.width + nx + x;
-                        let dst_idx = y * row_pitch + x * 4;
-
-                        // Create new Color32 and update old.pixels
-                        let new_color = Color32::from_rgba_premultiplied(
-                            255,
-                            255,
-                            255,
-                            (f.pixels[frac] * 255.) as u8,
-                        );
-                        old.pixels[whole] = new_color;
-
-                        // Update update_data
-                        let color_array = new_color.to_array();
-                        update_data[dst_idx..dst_idx + 4]
-                            .copy_from_slice(&color_array);
-                    }
+    ) -> Result<(bool, Option<D3D10_BOX>)> {
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 {
+            return Ok((false, None));
+        }
+        let patch = Self::to_pixels(image, premultiply);
+
+        // egui may extend a texture with a partial update, which is then
+        // recreated at the new size before the patch is written.
+        let (new_width, new_height) =
+            ((nx + width).max(old.width), (ny + height).max(old.height));
+        let grown = (new_width, new_height) != (old.width, old.height);
+        if grown {
+            Self::resize(device, ctx, srgb, old, new_width, new_height)?;
+        }
+
+        let rect = D3D10_BOX {
+            left: nx as u32,
+            top: ny as u32,
+            front: 0,
+            right: (nx + width) as u32,
+            bottom: (ny + height) as u32,
+            back: 1,
+        };
+        if let Some(pixels) = &mut old.pixels {
+            if width == old.width {
+                // full-width rows are contiguous in the mirror as well.
+                let start = ny * old.width;
+                pixels[start..start + patch.len()].copy_from_slice(&patch);
+            } else {
+                for (y, row) in patch.chunks_exact(width).enumerate() {
+                    let start = (ny + y) * old.width + nx;
+                    pixels[start..start + width].copy_from_slice(row);
                 }
+            }
+            return Ok((grown, Some(rect)));
+        }
+        // the pitch is the one of `patch`, which is tightly packed. the
+        // driver lays it out in the texture however it needs to.
+        unsafe {
+            ctx.UpdateSubresource(
+                &old.tex,
+                0,
+                Some(&rect),
+                patch.as_ptr() as _,
+                (width * mem::size_of::<Color32>()) as u32,
+                0,
+            );
+            if old.kind.mipmaps {
+                ctx.GenerateMips(&old.srv);
+            }
+        }
+        Ok((grown, None))
+    }
 
-                let subresource_data = D3D10_BOX {
-                    left: nx as u32,
-                    top: ny as u32,
-                    front: 0,
-                    right: (nx + f.width()) as u32,
-                    bottom: (ny + f.height()) as u32,
-                    back: 1,
-                };
+    /// Write `rect` of the CPU mirror of a texture to the GPU. Returns
+    /// whether the texture had to be recreated for that.
+    ///
+    /// A dynamic texture is written as a whole, as it can only be mapped with
+    /// `D3D10_MAP_WRITE_DISCARD`, and an immutable one is recreated from the
+    /// mirror. Any other texture is updated in place with `UpdateSubresource`,
+    /// and its mipmaps are generated again.
+    fn flush_mirror(
+        device: &ID3D10Device,
+        ctx: &ID3D10Device,
+        srgb: bool,
+        tex: &mut Texture,
+        rect: D3D10_BOX,
+    ) -> Result<bool> {
+        let Some(pixels) = &mut tex.pixels else {
+            return Ok(false);
+        };
+        match tex.kind.usage {
+            D3D10_USAGE_IMMUTABLE => {
+                *tex = Self::create_texture_from_pixels(
+                    device,
+                    mem::take(pixels),
+                    tex.width,
+                    tex.height,
+                    tex.kind,
+                    srgb,
+                )?;
+                return Ok(true);
+            },
+            D3D10_USAGE_DYNAMIC => {
+                Self::write_whole(&tex.tex, pixels, tex.width)?;
+            },
+            _ => unsafe {
+                let start = rect.top as usize * tex.width + rect.left as usize;
+                ctx.UpdateSubresource(
+                    &tex.tex,
+                    0,
+                    Some(&rect),
+                    pixels[start..].as_ptr() as _,
+                    (tex.width * mem::size_of::<Color32>()) as u32,
+                    0,
+                );
+                if tex.kind.mipmaps {
+                    ctx.GenerateMips(&tex.srv);
+                }
+            },
+        }
+        Ok(false)
+    }
 
-                unsafe {
-                    ctx.UpdateSubresource(
-                        &old.tex,
-                        0,
-                        Some(&subresource_data),
-                        update_data.as_ptr() as _,
-                        row_pitch as u32,
-                        0,
+    /// Recreate a texture at a larger size, keeping its content in the
+    /// top-left corner and leaving the rest transparent.
+    fn resize(
+        device: &ID3D10Device,
+        ctx: &ID3D10Device,
+        srgb: bool,
+        tex: &mut Texture,
+        width: usize,
+        height: usize,
+    ) -> Result<()> {
+        let mut pixels = vec![Color32::TRANSPARENT; width * height];
+        if let Some(old) = &tex.pixels {
+            for (y, row) in old.chunks_exact(tex.width).enumerate() {
+                pixels[y * width..y * width + tex.width].copy_from_slice(row);
+            }
+        }
+        let new = Self::create_texture_from_pixels(
+            device, pixels, width, height, tex.kind, srgb,
+        )?;
+        if tex.pixels.is_none() {
+            // without a mirror, the content is copied over on the GPU.
+            unsafe {
+                ctx.CopySubresourceRegion(
+                    &new.tex,
+                    0,
+                    0,
+                    0,
+                    0,
+                    &tex.tex,
+                    0,
+                    Some(&D3D10_BOX {
+                        left: 0,
+                        top: 0,
+                        front: 0,
+                        right: tex.width as u32,
+                        bottom: tex.height as u32,
+                        back: 1,
+                    }),
+                )
+            };
+        }
+        *tex = new;
+        Ok(())
+    }
+
+    /// Write all pixels of a dynamic texture, honoring the row pitch the
+    /// driver returns when mapping it.
+    fn write_whole(
+        tex: &ID3D10Texture2D,
+        pixels: &[Color32],
+        width: usize,
+    ) -> Result<()> {
+        unsafe {
+            let mapped = tex.Map(0, D3D10_MAP_WRITE_DISCARD, 0)?;
+            if mapped.RowPitch as usize == width * mem::size_of::<Color32>() {
+                // no padding between rows, so they are written at once.
+                std::ptr::copy_nonoverlapping(
+                    pixels.as_ptr(),
+                    mapped.pData as *mut Color32,
+                    pixels.len(),
+                );
+            } else {
+                for (y, row) in pixels.chunks_exact(width).enumerate() {
+                    std::ptr::copy_nonoverlapping(
+                        row.as_ptr(),
+                        (mapped.pData as *mut u8)
+                            .add(y * mapped.RowPitch as usize)
+                            as *mut Color32,
+                        width,
                     );
                 }
-            },
-            _ => unreachable!(),
+            }
+            tex.Unmap(0);
         }
         Ok(())
     }
 
+    /// Recreate every texture on `device` from the CPU copy of its pixels,
+    /// e.g. after the previous device has been removed. Textures without a
+    /// CPU mirror cannot be recreated and are dropped, and so are native
+    /// textures, which belong to the previous device. Their ids are kept
+    /// reserved for `update_native` and returned, along with the ones lost
+    /// by an earlier rebuild and not supplied again since.
+    ///
+    /// The pool is only modified if all textures are recreated successfully.
+    pub fn rebuild_on_device(
+        &mut self,
+        device: &ID3D10Device,
+    ) -> Result<Vec<TextureId>> {
+        let mut pool = HashMap::with_capacity(self.pool.len());
+        for (&tid, tex) in &self.pool {
+            let Some(pixels) = &tex.pixels else {
+                continue;
+            };
+            let tex = Self::create_texture_from_pixels(
+                device,
+                pixels.clone(),
+                tex.width,
+                tex.height,
+                tex.kind,
+                self.srgb,
+            )?;
+            if self.debug_names {
+                Self::set_debug_name(tid, &tex)?;
+            }
+            pool.insert(tid, tex);
+        }
+        self.pool = pool;
+        self.lost_native
+            .extend(self.native.drain().map(|(id, _)| id));
+        self.device = device.clone();
+        let mut lost = self.lost_native.iter().copied().collect::<Vec<_>>();
+        lost.sort_unstable();
+        Ok(lost.into_iter().map(TextureId::User).collect())
+    }
+
     fn create_texture(
         device: &ID3D10Device,
         data: ImageData,
+        kind: TextureKind,
+        srgb: bool,
+        premultiply: bool,
     ) -> Result<Texture> {
-        let width = data.width();
+        let (width, height) = (data.width(), data.height());
+        Self::create_texture_from_pixels(
+            device,
+            Self::to_pixels(data, premultiply),
+            width,
+            height,
+            kind,
+            srgb,
+        )
+    }
 
-        let pixels = match &data {
-            ImageData::Color(c) => c.pixels.clone(),
+    fn to_pixels(data: ImageData, premultiply: bool) -> Vec<Color32> {
+        let mut pixels = match data {
+            ImageData::Color(c) => Arc::try_unwrap(c)
+                .map_or_else(|c| c.pixels.clone(), |c| c.pixels),
             ImageData::Font(f) => f
                 .pixels
                 .iter()
@@ -153,41 +848,154 @@ impl TexturePool {
                 })
                 .collect(),
         };
+        if premultiply {
+            for pixel in &mut pixels {
+                let [r, g, b, a] = pixel.to_array();
+                // rounded to the nearest value, so that opaque pixels stay
+                // the same.
+                let scale = |c: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+                *pixel = Color32::from_rgba_premultiplied(
+                    scale(r),
+                    scale(g),
+                    scale(b),
+                    a,
+                );
+            }
+        }
+        pixels
+    }
+
+    fn create_srv(
+        device: &ID3D10Device,
+        tex: &ID3D10Texture2D,
+        srgb: bool,
+    ) -> Result<ID3D10ShaderResourceView> {
+        let desc = D3D10_SHADER_RESOURCE_VIEW_DESC {
+            Format: if srgb {
+                DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+            } else {
+                DXGI_FORMAT_R8G8B8A8_UNORM
+            },
+            ViewDimension: D3D_SRV_DIMENSION_TEXTURE2D,
+            Anonymous: D3D10_SHADER_RESOURCE_VIEW_DESC_0 {
+                // all mip levels
+                Texture2D: D3D10_TEX2D_SRV {
+                    MostDetailedMip: 0,
+                    MipLevels: u32::MAX,
+                },
+            },
+        };
+        let mut srv = None;
+        unsafe {
+            device.CreateShaderResourceView(tex, Some(&desc), Some(&mut srv))
+        }
+        .map_err(Error::create("shader resource view"))?;
+        Ok(srv.unwrap())
+    }
 
+    fn create_texture_from_pixels(
+        device: &ID3D10Device,
+        pixels: Vec<Color32>,
+        width: usize,
+        height: usize,
+        kind: TextureKind,
+        srgb: bool,
+    ) -> Result<Texture> {
+        let cpu_access = if kind.usage == D3D10_USAGE_DYNAMIC {
+            D3D10_CPU_ACCESS_WRITE.0 as _
+        } else {
+            0
+        };
+        let (mip_levels, bind_flags, misc_flags) = if kind.mipmaps {
+            (
+                0,
+                D3D10_BIND_SHADER_RESOURCE.0 | D3D10_BIND_RENDER_TARGET.0,
+                D3D10_RESOURCE_MISC_GENERATE_MIPS.0,
+            )
+        } else {
+            (1, D3D10_BIND_SHADER_RESOURCE.0, 0)
+        };
         let desc = D3D10_TEXTURE2D_DESC {
-            Width: data.width() as _,
-            Height: data.height() as _,
-            MipLevels: 1,
+            Width: width as _,
+            Height: height as _,
+            MipLevels: mip_levels,
             ArraySize: 1,
-            Format: DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+            // typeless, so that `set_srgb` only needs to recreate the views.
+            Format: DXGI_FORMAT_R8G8B8A8_TYPELESS,
             SampleDesc: DXGI_SAMPLE_DESC {
                 Count: 1,
                 Quality: 0,
             },
-            Usage: D3D10_USAGE_DYNAMIC,
-            BindFlags: D3D10_BIND_SHADER_RESOURCE.0 as _,
-            CPUAccessFlags: D3D10_CPU_ACCESS_WRITE.0 as _,
-            ..Default::default()
+            Usage: kind.usage,
+            BindFlags: bind_flags as _,
+            CPUAccessFlags: cpu_access,
+            MiscFlags: misc_flags as _,
         };
 
+        // the pitch of `pixels`, not of the texture, as with
+        // `UpdateSubresource` in `update_partial`.
+        let pitch = (width * mem::size_of::<Color32>()) as u32;
         let subresource_data = D3D10_SUBRESOURCE_DATA {
             pSysMem: pixels.as_ptr() as _,
-            SysMemPitch: (width * mem::size_of::<Color32>()) as u32,
+            SysMemPitch: pitch,
             SysMemSlicePitch: 0,
         };
 
-        let tex =
-            unsafe { device.CreateTexture2D(&desc, Some(&subresource_data)) }?;
+        // initial data would be needed for every mip level, so a mipmapped
+        // texture is filled and mipmapped afterwards.
+        let tex = unsafe {
+            device.CreateTexture2D(
+                &desc,
+                (!kind.mipmaps).then_some(&subresource_data as _),
+            )
+        }
+        .map_err(Error::create("texture"))?;
 
-        let mut srv = None;
-        unsafe { device.CreateShaderResourceView(&tex, None, Some(&mut srv)) }?;
-        let srv = srv.unwrap();
+        let srv = Self::create_srv(device, &tex, srgb)?;
+        if kind.mipmaps {
+            unsafe {
+                device.UpdateSubresource(
+                    &tex,
+                    0,
+                    None,
+                    pixels.as_ptr() as _,
+                    pitch,
+                    0,
+                );
+                device.GenerateMips(&srv);
+            }
+        }
 
         Ok(Texture {
             tex,
             srv,
             width,
-            pixels,
+            height,
+            kind,
+            pixels: kind.mirror.then_some(pixels),
         })
     }
 }
+
+/// Report what is left in the pool when it goes away, to help finding
+/// textures that are never freed.
+#[cfg(all(debug_assertions, feature = "logging"))]
+impl Drop for TexturePool {
+    fn drop(&mut self) {
+        if self.pool.is_empty() && self.native.is_empty() {
+            return;
+        }
+        let usage = self.memory_usage();
+        log::debug!(
+            concat!(
+                "dropping {} egui textures taking {} bytes on the GPU and {} ",
+                "bytes of CPU mirrors, and {} native textures taking {} bytes."
+            ),
+            usage.texture_count,
+            usage.gpu_bytes,
+            usage.mirror_bytes,
+            self.native.len(),
+            self.native.values().map(|t| t.bytes).sum::<usize>(),
+        );
+    }
+}
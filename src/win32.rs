@@ -0,0 +1,288 @@
+//! Input handling for applications running their own Win32 message loop
+//! instead of `winit`. This module requires the `win32` feature.
+
+use std::time::Instant;
+
+use egui::{
+    Event, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect,
+    Vec2,
+};
+
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, RECT, WPARAM},
+    UI::{
+        HiDpi::GetDpiForWindow,
+        Input::KeyboardAndMouse::*,
+        WindowsAndMessaging::{
+            GetClientRect, USER_DEFAULT_SCREEN_DPI, WHEEL_DELTA, WM_CHAR,
+            WM_DPICHANGED, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDOWN,
+            WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL,
+            WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP,
+            WM_SETFOCUS, WM_SIZE, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN,
+            WM_XBUTTONUP,
+        },
+    },
+};
+
+// part of `Win32_UI_Controls`, which is not worth another feature.
+const WM_MOUSELEAVE: u32 = 0x02A3;
+
+/// Translates the messages of a window into [`egui::RawInput`], and keeps
+/// track of the scale factor of the window.
+///
+/// Pass every message the window procedure receives to
+/// [`Win32Input::handle_message`], then once per frame pass
+/// [`Win32Input::take_raw_input`] to [`egui::Context::run`] and render with
+/// [`Win32Input::scale_factor`] as the scale factor. The messages still need
+/// to be passed on to `DefWindowProcW` as usual.
+///
+/// Mouse, keyboard, text, focus and DPI changes are handled. Clipboard
+/// pastes, IME composition and the [`egui::PlatformOutput`], e.g. the cursor
+/// icon or copied text, are left to the application. The window should be
+/// per-monitor DPI aware for the scale factor to be meaningful.
+pub struct Win32Input {
+    hwnd: HWND,
+    start: Instant,
+    scale_factor: f32,
+    raw_input: RawInput,
+    tracking_mouse: bool,
+    // the first half of a character outside of the basic multilingual
+    // plane, which arrives as two `WM_CHAR` messages.
+    high_surrogate: Option<u16>,
+}
+
+impl Win32Input {
+    /// Start collecting the input of the window `hwnd`.
+    pub fn new(hwnd: HWND) -> Self {
+        Self {
+            hwnd,
+            start: Instant::now(),
+            scale_factor: Self::dpi_scale(unsafe { GetDpiForWindow(hwnd) }),
+            raw_input: RawInput::default(),
+            tracking_mouse: false,
+            high_surrogate: None,
+        }
+    }
+
+    /// Get the scale factor of the window, to pass to
+    /// [`Renderer::render`](crate::Renderer::render).
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    /// Translate a message of the window into input for `egui`. Returns
+    /// whether the message was relevant to `egui`, i.e. whether the window
+    /// should be repainted.
+    ///
+    /// On `WM_DPICHANGED`, the scale factor is updated, but resizing the
+    /// window to the suggested rect is still up to the application.
+    pub fn handle_message(
+        &mut self,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> bool {
+        let modifiers = Self::modifiers();
+        self.raw_input.modifiers = modifiers;
+        let event = match msg {
+            WM_MOUSEMOVE => {
+                if !self.tracking_mouse {
+                    // ask for `WM_MOUSELEAVE` once the pointer leaves.
+                    let mut track = TRACKMOUSEEVENT {
+                        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as _,
+                        dwFlags: TME_LEAVE,
+                        hwndTrack: self.hwnd,
+                        dwHoverTime: 0,
+                    };
+                    self.tracking_mouse =
+                        unsafe { TrackMouseEvent(&mut track) }.is_ok();
+                }
+                Event::PointerMoved(self.pos(lparam))
+            },
+            WM_MOUSELEAVE => {
+                self.tracking_mouse = false;
+                Event::PointerGone
+            },
+            WM_LBUTTONDOWN | WM_LBUTTONUP | WM_RBUTTONDOWN | WM_RBUTTONUP
+            | WM_MBUTTONDOWN | WM_MBUTTONUP | WM_XBUTTONDOWN | WM_XBUTTONUP => {
+                let button = match msg {
+                    WM_LBUTTONDOWN | WM_LBUTTONUP => PointerButton::Primary,
+                    WM_RBUTTONDOWN | WM_RBUTTONUP => PointerButton::Secondary,
+                    WM_MBUTTONDOWN | WM_MBUTTONUP => PointerButton::Middle,
+                    // XBUTTON1 or XBUTTON2 in the high word.
+                    _ if (wparam.0 >> 16) & 0xffff == 1 => {
+                        PointerButton::Extra1
+                    },
+                    _ => PointerButton::Extra2,
+                };
+                Event::PointerButton {
+                    pos: self.pos(lparam),
+                    button,
+                    pressed: matches!(
+                        msg,
+                        WM_LBUTTONDOWN
+                            | WM_RBUTTONDOWN
+                            | WM_MBUTTONDOWN
+                            | WM_XBUTTONDOWN
+                    ),
+                    modifiers,
+                }
+            },
+            WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+                let lines = (wparam.0 >> 16) as i16 as f32 / WHEEL_DELTA as f32;
+                Event::MouseWheel {
+                    unit: MouseWheelUnit::Line,
+                    // positive is away from the user for the vertical wheel,
+                    // but to the right for the horizontal one.
+                    delta: if msg == WM_MOUSEWHEEL {
+                        Vec2::new(0.0, lines)
+                    } else {
+                        Vec2::new(-lines, 0.0)
+                    },
+                    modifiers,
+                }
+            },
+            WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP => {
+                let Some(key) = Self::key(VIRTUAL_KEY(wparam.0 as u16)) else {
+                    return false;
+                };
+                let pressed = matches!(msg, WM_KEYDOWN | WM_SYSKEYDOWN);
+                if pressed && modifiers.command && !modifiers.alt {
+                    match key {
+                        Key::C => self.raw_input.events.push(Event::Copy),
+                        Key::X => self.raw_input.events.push(Event::Cut),
+                        _ => {},
+                    }
+                }
+                Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed,
+                    // the previous key state is bit 30.
+                    repeat: pressed && (lparam.0 >> 30) & 1 == 1,
+                    modifiers,
+                }
+            },
+            WM_CHAR => {
+                let unit = wparam.0 as u16;
+                let c = if (0xd800..0xdc00).contains(&unit) {
+                    self.high_surrogate = Some(unit);
+                    return false;
+                } else if let Some(high) = self.high_surrogate.take() {
+                    char::decode_utf16([high, unit]).next()
+                } else {
+                    char::decode_utf16([unit]).next()
+                };
+                match c {
+                    // control characters are handled as keys.
+                    Some(Ok(c)) if !c.is_control() => {
+                        Event::Text(c.to_string())
+                    },
+                    _ => return false,
+                }
+            },
+            WM_SETFOCUS | WM_KILLFOCUS => {
+                self.raw_input.focused = msg == WM_SETFOCUS;
+                Event::WindowFocused(self.raw_input.focused)
+            },
+            WM_DPICHANGED => {
+                // the new DPI is in both words of `wparam`.
+                self.scale_factor = Self::dpi_scale(wparam.0 as u32 & 0xffff);
+                return true;
+            },
+            WM_SIZE => return true,
+            _ => return false,
+        };
+        self.raw_input.events.push(event);
+        true
+    }
+
+    /// Take the input collected since the previous call, along with the
+    /// current size of the client area of the window and the time.
+    pub fn take_raw_input(&mut self) -> RawInput {
+        let mut rect = RECT::default();
+        self.raw_input.screen_rect =
+            unsafe { GetClientRect(self.hwnd, &mut rect) }
+                .ok()
+                .map(|()| {
+                    Rect::from_min_size(
+                        Pos2::ZERO,
+                        Vec2::new(
+                            (rect.right - rect.left) as f32,
+                            (rect.bottom - rect.top) as f32,
+                        ) / self.scale_factor,
+                    )
+                });
+        self.raw_input.time = Some(self.start.elapsed().as_secs_f64());
+        self.raw_input
+            .viewports
+            .entry(self.raw_input.viewport_id)
+            .or_default()
+            .native_pixels_per_point = Some(self.scale_factor);
+        self.raw_input.take()
+    }
+
+    fn dpi_scale(dpi: u32) -> f32 {
+        // `GetDpiForWindow` returns 0 for an invalid window.
+        match dpi {
+            0 => 1.0,
+            dpi => dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+        }
+    }
+
+    /// Convert the client coordinates in `lparam` of a mouse message into
+    /// points.
+    fn pos(&self, lparam: LPARAM) -> Pos2 {
+        // signed, as they are negative left of or above the client area.
+        let x = (lparam.0 & 0xffff) as i16 as f32;
+        let y = ((lparam.0 >> 16) & 0xffff) as i16 as f32;
+        Pos2::new(x, y) / self.scale_factor
+    }
+
+    fn modifiers() -> Modifiers {
+        let down = |key: VIRTUAL_KEY| unsafe { GetKeyState(key.0 as _) } < 0;
+        let ctrl = down(VK_CONTROL);
+        Modifiers {
+            alt: down(VK_MENU),
+            ctrl,
+            shift: down(VK_SHIFT),
+            mac_cmd: false,
+            command: ctrl,
+        }
+    }
+
+    fn key(vk: VIRTUAL_KEY) -> Option<Key> {
+        Some(match vk {
+            VK_DOWN => Key::ArrowDown,
+            VK_LEFT => Key::ArrowLeft,
+            VK_RIGHT => Key::ArrowRight,
+            VK_UP => Key::ArrowUp,
+            VK_ESCAPE => Key::Escape,
+            VK_TAB => Key::Tab,
+            VK_BACK => Key::Backspace,
+            VK_RETURN => Key::Enter,
+            VK_SPACE => Key::Space,
+            VK_INSERT => Key::Insert,
+            VK_DELETE => Key::Delete,
+            VK_HOME => Key::Home,
+            VK_END => Key::End,
+            VK_PRIOR => Key::PageUp,
+            VK_NEXT => Key::PageDown,
+            VK_OEM_MINUS | VK_SUBTRACT => Key::Minus,
+            VK_OEM_PLUS | VK_ADD => Key::Plus,
+            VK_OEM_COMMA => Key::Comma,
+            VK_OEM_PERIOD | VK_DECIMAL => Key::Period,
+            // letters and digits share their codes with ASCII.
+            VIRTUAL_KEY(code @ (0x30..=0x39 | 0x41..=0x5a)) => {
+                return Key::from_name(&char::from(code as u8).to_string());
+            },
+            VIRTUAL_KEY(numpad @ 0x60..=0x69) => {
+                return Key::from_name(&(numpad - VK_NUMPAD0.0).to_string());
+            },
+            VIRTUAL_KEY(f @ 0x70..=0x83) => {
+                return Key::from_name(&format!("F{}", f - VK_F1.0 + 1));
+            },
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,140 @@
+//! Shared setup of the integration tests.
+//!
+//! Every test renders on a WARP (software rasterizer) device into an
+//! offscreen texture, so the tests do not need a GPU or a window and can run
+//! on any Windows CI machine with `cargo test`.
+
+// each test crate only uses some of the helpers.
+#![allow(dead_code)]
+
+use egui::{pos2, vec2, Rect, ViewportId};
+
+use egui_directx10::{Renderer, RendererConfig, RendererOutput, Result};
+
+use windows::{
+    core::Interface,
+    Win32::Graphics::{Direct3D10::*, Dxgi::Common::*},
+};
+
+pub const FRAME_WIDTH: u32 = 256;
+pub const FRAME_HEIGHT: u32 = 256;
+
+/// A WARP device with an offscreen render target, an `egui` context and a
+/// renderer.
+pub struct Harness {
+    pub device: ID3D10Device,
+    pub texture: ID3D10Texture2D,
+    pub render_target: ID3D10RenderTargetView,
+    pub egui_ctx: egui::Context,
+    pub renderer: Renderer,
+}
+
+impl Harness {
+    /// Set up a render target of `format` and a renderer with `config`.
+    pub fn new(format: DXGI_FORMAT, config: RendererConfig) -> Result<Self> {
+        let device = egui_directx10::create_warp_device()?;
+        let renderer = Renderer::with_config(&device, config)?;
+        let (texture, render_target) = renderer.create_offscreen_target(
+            FRAME_WIDTH,
+            FRAME_HEIGHT,
+            format,
+        )?;
+        Ok(Self {
+            device,
+            texture,
+            render_target,
+            egui_ctx: egui::Context::default(),
+            renderer,
+        })
+    }
+
+    /// Run a frame of `ui` covering the whole render target, as a window with
+    /// a scale factor of `scale_factor` would. The screen rect is in points,
+    /// so it shrinks with [`egui::Context::zoom_factor`] too.
+    pub fn run(
+        &self,
+        scale_factor: f32,
+        ui: impl FnMut(&egui::Context),
+    ) -> egui::FullOutput {
+        let pixels_per_point = scale_factor * self.egui_ctx.zoom_factor();
+        let mut egui_input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                pos2(0.0, 0.0),
+                vec2(FRAME_WIDTH as _, FRAME_HEIGHT as _) / pixels_per_point,
+            )),
+            ..Default::default()
+        };
+        egui_input
+            .viewports
+            .entry(ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(scale_factor);
+        self.egui_ctx.run(egui_input, ui)
+    }
+
+    pub fn render(
+        &mut self,
+        output: RendererOutput,
+        scale_factor: f32,
+    ) -> Result<()> {
+        self.renderer.render(
+            &self.device,
+            &self.render_target,
+            &self.egui_ctx,
+            output,
+            scale_factor,
+        )
+    }
+
+    /// Read back the render target, see [`Renderer::read_pixels`].
+    pub fn read_pixels(&self) -> Result<Vec<u8>> {
+        let (_, _, pixels) =
+            Renderer::read_pixels(&self.device, &self.render_target)?;
+        Ok(pixels)
+    }
+}
+
+/// Read back the top mip level of the texture behind `srv`, e.g. a texture
+/// uploaded by `egui`. Returns its width, height and tightly packed rows of 4
+/// bytes per pixel.
+pub fn read_texture(
+    device: &ID3D10Device,
+    srv: &ID3D10ShaderResourceView,
+) -> Result<(u32, u32, Vec<u8>)> {
+    let texture = unsafe { srv.GetResource() }?.cast::<ID3D10Texture2D>()?;
+    let mut desc = D3D10_TEXTURE2D_DESC::default();
+    unsafe { texture.GetDesc(&mut desc) };
+    let staging = unsafe {
+        device.CreateTexture2D(
+            &D3D10_TEXTURE2D_DESC {
+                Usage: D3D10_USAGE_STAGING,
+                BindFlags: 0,
+                CPUAccessFlags: D3D10_CPU_ACCESS_READ.0 as _,
+                MiscFlags: 0,
+                ..desc
+            },
+            None,
+        )
+    }?;
+    unsafe { device.CopyResource(&staging, &texture) };
+
+    let mapped = unsafe { staging.Map(0, D3D10_MAP_READ, 0) }?;
+    let row_size = desc.Width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_size * desc.Height as usize);
+    for y in 0..desc.Height as usize {
+        pixels.extend_from_slice(unsafe {
+            std::slice::from_raw_parts(
+                (mapped.pData as *const u8).add(y * mapped.RowPitch as usize),
+                row_size,
+            )
+        });
+    }
+    unsafe { staging.Unmap(0) };
+    Ok((desc.Width, desc.Height, pixels))
+}
+
+/// Get the pixel at `(x, y)` out of pixels read back from the render target.
+pub fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let offset = (y * FRAME_WIDTH + x) as usize * 4;
+    pixels[offset..offset + 4].try_into().unwrap()
+}
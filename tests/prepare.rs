@@ -0,0 +1,177 @@
+//! Tests of the meshes the renderer prepares without drawing them.
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use common::Harness;
+
+use egui::{epaint::ClippedShape, pos2, vec2, Color32, Mesh, Rect, Shape};
+
+use egui_directx10::RendererOutput;
+
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+/// A harness whose `egui` context has run a frame, so that it can tessellate.
+fn harness() -> egui_directx10::Result<Harness> {
+    let harness =
+        Harness::new(DXGI_FORMAT_R8G8B8A8_UNORM_SRGB, Default::default())?;
+    let _ = harness.run(1.0, |_| ());
+    Ok(harness)
+}
+
+/// A filled rect at `rect` in points, clipped to itself.
+fn rect_shape(rect: Rect) -> ClippedShape {
+    ClippedShape {
+        clip_rect: rect,
+        shape: Shape::rect_filled(rect, 0.0, Color32::RED),
+    }
+}
+
+/// Meshes clipped out of the frame are not counted, just like
+/// [`egui_directx10::Renderer::render`] does not draw them.
+#[test]
+fn estimate_cost_skips_culled_meshes() -> egui_directx10::Result<()> {
+    let Harness {
+        egui_ctx, renderer, ..
+    } = harness()?;
+    let visible = Rect::from_min_size(pos2(10.0, 10.0), vec2(20.0, 20.0));
+    let output = RendererOutput {
+        textures_delta: Default::default(),
+        shapes: vec![
+            rect_shape(visible),
+            rect_shape(visible.translate(vec2(500.0, 0.0))),
+        ],
+        pixels_per_point: 1.0,
+    };
+    let cost = renderer.estimate_cost(&egui_ctx, &output, 1.0, (256, 256));
+    assert_eq!(cost.mesh_count, 1);
+    let meshes = renderer.prepare_meshes(&egui_ctx, &output, 1.0, (256, 256));
+    assert_eq!(cost.vertex_count, meshes[0].vertices.len());
+    assert_eq!(cost.index_count, meshes[0].indices.len());
+    Ok(())
+}
+
+/// Warnings about the meshes go to the diagnostic handler of the renderer,
+/// just like while rendering.
+#[test]
+fn prepare_meshes_reports_to_diagnostic_handler() -> egui_directx10::Result<()>
+{
+    let Harness {
+        egui_ctx,
+        mut renderer,
+        ..
+    } = harness()?;
+    let warnings = Rc::new(RefCell::new(Vec::new()));
+    renderer.set_diagnostic_handler(Some(Box::new({
+        let warnings = warnings.clone();
+        move |warning: &str| warnings.borrow_mut().push(warning.to_owned())
+    })));
+
+    let rect = Rect::from_min_size(pos2(10.0, 10.0), vec2(20.0, 20.0));
+    let mut mesh = Mesh::default();
+    mesh.add_colored_rect(rect, Color32::RED);
+    // an incomplete trailing triangle.
+    mesh.indices.push(0);
+    let output = RendererOutput {
+        textures_delta: Default::default(),
+        shapes: vec![ClippedShape {
+            clip_rect: rect,
+            shape: Shape::mesh(mesh),
+        }],
+        pixels_per_point: 1.0,
+    };
+    let meshes = renderer.prepare_meshes(&egui_ctx, &output, 1.0, (256, 256));
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(meshes[0].indices.len(), 6);
+    assert_eq!(warnings.borrow().len(), 1, "{:?}", warnings.borrow());
+    Ok(())
+}
+
+/// A mesh with indices but no vertices is skipped rather than uploaded, in
+/// release builds too, where out of range indices are not checked.
+#[test]
+fn mesh_without_vertices_is_skipped() -> egui_directx10::Result<()> {
+    let mut harness = harness()?;
+    let rect = Rect::from_min_size(pos2(10.0, 10.0), vec2(20.0, 20.0));
+    let mesh = Mesh {
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    };
+    let output = || RendererOutput {
+        textures_delta: Default::default(),
+        shapes: vec![ClippedShape {
+            clip_rect: rect,
+            shape: Shape::mesh(mesh.clone()),
+        }],
+        pixels_per_point: 1.0,
+    };
+    let meshes = harness.renderer.prepare_meshes(
+        &harness.egui_ctx,
+        &output(),
+        1.0,
+        (256, 256),
+    );
+    assert!(meshes.is_empty());
+    harness.render(output(), 1.0)?;
+    assert_eq!(harness.renderer.last_render_stats().mesh_count, 0);
+    Ok(())
+}
+
+/// Vertex positions and clip rects are scaled by the same number of physical
+/// pixels per point, the scale factor times the zoom factor, so the vertices
+/// of a rect clipped to itself end up exactly on its scissor rect, give or
+/// take the half pixel of feathering `egui` adds around it.
+#[test]
+fn vertices_and_clip_rects_agree() -> egui_directx10::Result<()> {
+    // whole physical pixels at every combination below, so that rounding
+    // the scissor rects does not move them.
+    let rects = [
+        Rect::from_min_max(pos2(8.0, 8.0), pos2(40.0, 40.0)),
+        Rect::from_min_max(pos2(48.0, 16.0), pos2(72.0, 64.0)),
+    ];
+    for scale_factor in [1.0, 1.5, 2.0] {
+        for zoom_factor in [1.0, 1.25, 1.5] {
+            let harness = harness()?;
+            harness.egui_ctx.set_zoom_factor(zoom_factor);
+            let _ = harness.run(scale_factor, |_| ());
+            let pixels_per_point = scale_factor * zoom_factor;
+            let output = RendererOutput {
+                textures_delta: Default::default(),
+                shapes: rects.into_iter().map(rect_shape).collect(),
+                pixels_per_point,
+            };
+            let meshes = harness.renderer.prepare_meshes(
+                &harness.egui_ctx,
+                &output,
+                scale_factor,
+                (common::FRAME_WIDTH, common::FRAME_HEIGHT),
+            );
+
+            let case = format!("scale {scale_factor}, zoom {zoom_factor}");
+            assert_eq!(meshes.len(), rects.len(), "meshes at {case}");
+            for (mesh, rect) in meshes.iter().zip(rects) {
+                assert_eq!(mesh.clip_rect, rect * pixels_per_point, "{case}");
+                let extent = mesh
+                    .vertices
+                    .iter()
+                    .fold(Rect::NOTHING, |extent, vertex| {
+                        extent.union(Rect::from_pos(vertex.pos))
+                    })
+                    * pixels_per_point;
+                for (vertex, clip) in [
+                    (extent.left(), mesh.clip_rect.left()),
+                    (extent.top(), mesh.clip_rect.top()),
+                    (extent.right(), mesh.clip_rect.right()),
+                    (extent.bottom(), mesh.clip_rect.bottom()),
+                ] {
+                    assert!(
+                        (vertex - clip).abs() <= 0.5 + 1e-3,
+                        "vertex at {vertex} but clipped at {clip} at {case}"
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,285 @@
+//! Tests of the pixels the renderer draws, read back from the render target.
+
+mod common;
+
+use common::{pixel, Harness, FRAME_HEIGHT, FRAME_WIDTH};
+
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use egui::{
+    epaint::ClippedShape, pos2, vec2, Align2, Color32, ColorImage, FontId,
+    LayerId, Rect, Shape, Stroke,
+};
+
+use egui_directx10::{
+    BlendMode, PaintCallbackFn, Renderer, RendererConfig, RendererOutput,
+};
+
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+/// Paint a rect of `color` at `rect` in points onto the background.
+fn paint_rect(ctx: &egui::Context, rect: Rect, color: Color32) {
+    ctx.layer_painter(LayerId::background())
+        .rect_filled(rect, 0.0, color);
+}
+
+/// Rendering a semi-transparent rect with [`BlendMode::PremultipliedAlpha`]
+/// into a texture cleared to transparent, then copying that texture as is,
+/// yields the premultiplied color of the rect and exact zero elsewhere.
+#[test]
+fn premultiplied_alpha_survives_copy() -> egui_directx10::Result<()> {
+    let mut harness = Harness::new(
+        DXGI_FORMAT_R8G8B8A8_UNORM,
+        RendererConfig {
+            gamma_output: true,
+            blend_mode: BlendMode::PremultipliedAlpha,
+            clear_color: Some([0.0; 4]),
+            ..Default::default()
+        },
+    )?;
+    let rect = Rect::from_min_size(pos2(64.0, 64.0), vec2(64.0, 64.0));
+    let color = Color32::from_rgba_unmultiplied(255, 0, 0, 128);
+    let output = harness.run(1.0, |ctx| paint_rect(ctx, rect, color));
+    harness.render(egui_directx10::split_output(output).renderer, 1.0)?;
+
+    // the copy stands in for the swap chain the texture is copied onto.
+    let (copy, copy_target) = harness.renderer.create_offscreen_target(
+        FRAME_WIDTH,
+        FRAME_HEIGHT,
+        DXGI_FORMAT_R8G8B8A8_UNORM,
+    )?;
+    unsafe { harness.device.CopyResource(&copy, &harness.texture) };
+    let (_, _, pixels) = Renderer::read_pixels(&harness.device, &copy_target)?;
+
+    let [r, g, b, a] = pixel(&pixels, 96, 96);
+    assert!(a.abs_diff(color.a()) <= 1, "alpha {a} inside the rect");
+    assert!(r.abs_diff(color.r()) <= 1, "red {r} inside the rect");
+    assert_eq!((g, b), (0, 0), "green and blue inside the rect");
+    for (x, y) in [(0, 0), (32, 96), (160, 96), (96, 32), (255, 255)] {
+        assert_eq!(pixel(&pixels, x, y), [0; 4], "pixel at ({x}, {y})");
+    }
+    Ok(())
+}
+
+/// [`Renderer::render_full_output`] puts a rect painted at a known position in
+/// points exactly at that position times the scale factor times the zoom
+/// factor in physical pixels, and so does [`Renderer::render`].
+#[test]
+fn full_output_scales_once() -> egui_directx10::Result<()> {
+    let rect = Rect::from_min_size(pos2(10.0, 10.0), vec2(20.0, 20.0));
+    for (scale_factor, zoom_factor) in
+        [(1.0, 1.0), (1.0, 1.5), (2.0, 1.0), (2.0, 1.5)]
+    {
+        let mut harness = Harness::new(
+            DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+            RendererConfig {
+                clear_color: Some([0.0, 0.0, 0.0, 1.0]),
+                ..Default::default()
+            },
+        )?;
+        // the zoom factor applies from the next frame on, which also uploads
+        // the font atlas the rect samples.
+        harness.egui_ctx.set_zoom_factor(zoom_factor);
+        let output = harness.run(scale_factor, |_| ());
+        harness.renderer.render_full_output(
+            &harness.device,
+            &harness.render_target,
+            &harness.egui_ctx,
+            output,
+        )?;
+        assert_eq!(harness.egui_ctx.zoom_factor(), zoom_factor);
+
+        let output = harness
+            .run(scale_factor, |ctx| paint_rect(ctx, rect, Color32::RED));
+        assert_eq!(output.pixels_per_point, scale_factor * zoom_factor);
+        harness.renderer.render_full_output(
+            &harness.device,
+            &harness.render_target,
+            &harness.egui_ctx,
+            output.clone(),
+        )?;
+        let pixels = harness.read_pixels()?;
+
+        // a margin of two pixels around the edges leaves out the feathering.
+        let pixels_rect = rect * scale_factor * zoom_factor;
+        let (left, top) = (pixels_rect.left() as u32, pixels_rect.top() as u32);
+        let (right, bottom) =
+            (pixels_rect.right() as u32, pixels_rect.bottom() as u32);
+        let case = format!("scale {scale_factor}, zoom {zoom_factor}");
+        for (x, y) in [(left + 2, top + 2), (right - 3, bottom - 3)] {
+            assert_eq!(
+                pixel(&pixels, x, y),
+                [255, 0, 0, 255],
+                "pixel ({x}, {y}) inside the rect at {case}"
+            );
+        }
+        for (x, y) in [(left - 3, top - 3), (right + 2, bottom + 2)] {
+            assert_eq!(
+                pixel(&pixels, x, y),
+                [0, 0, 0, 255],
+                "pixel ({x}, {y}) outside the rect at {case}"
+            );
+        }
+
+        harness.render(
+            egui_directx10::split_output(output).renderer,
+            scale_factor,
+        )?;
+        assert_eq!(harness.read_pixels()?, pixels, "render at {case}");
+    }
+    Ok(())
+}
+
+/// A UI drawn across the whole frame, i.e. with every mesh clipped to the
+/// whole frame, takes the batched path of the renderer. Adding a paint
+/// callback, which does not draw anything, forces the general per-mesh path
+/// for the same meshes, which must produce the same pixels.
+#[test]
+fn batched_path_matches_per_mesh_path() -> egui_directx10::Result<()> {
+    let mut harness = Harness::new(
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+        RendererConfig {
+            clear_color: Some([0.0, 0.0, 0.0, 1.0]),
+            ..Default::default()
+        },
+    )?;
+    let texture = harness.egui_ctx.load_texture(
+        "green",
+        ColorImage::new([4, 4], Color32::GREEN),
+        Default::default(),
+    );
+    // overlapping shapes that alternate between two textures, so that they
+    // end up in several meshes and the draw order shows.
+    let ui = |ctx: &egui::Context| {
+        let painter = ctx.layer_painter(LayerId::background());
+        for i in 0..8 {
+            let min = pos2(i as f32 * 24.0, i as f32 * 16.0);
+            painter.rect_filled(
+                Rect::from_min_size(min, vec2(96.0, 64.0)),
+                8.0,
+                Color32::from_rgba_unmultiplied(255, i * 32, 0, 160),
+            );
+            painter.image(
+                texture.id(),
+                Rect::from_min_size(min + vec2(64.0, 8.0), vec2(24.0, 24.0)),
+                Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                Color32::from_white_alpha(192),
+            );
+            painter.circle_stroke(
+                min + vec2(48.0, 32.0),
+                20.0,
+                Stroke::new(2.0, Color32::LIGHT_BLUE),
+            );
+            painter.text(
+                min,
+                Align2::LEFT_TOP,
+                format!("mesh {i}"),
+                FontId::proportional(14.0),
+                Color32::WHITE,
+            );
+        }
+    };
+    // the first frame uploads the textures, including the glyphs.
+    let output = harness.run(1.0, ui);
+    harness.render(egui_directx10::split_output(output).renderer, 1.0)?;
+    let mut output =
+        egui_directx10::split_output(harness.run(1.0, ui)).renderer;
+
+    harness.render(
+        RendererOutput {
+            textures_delta: std::mem::take(&mut output.textures_delta),
+            shapes: output.shapes.clone(),
+            pixels_per_point: output.pixels_per_point,
+        },
+        1.0,
+    )?;
+    let batched_stats = harness.renderer.last_render_stats();
+    let batched = harness.read_pixels()?;
+
+    let screen_rect = harness.egui_ctx.screen_rect();
+    output.shapes.push(ClippedShape {
+        clip_rect: screen_rect,
+        shape: Shape::Callback(egui::PaintCallback {
+            rect: Rect::from_min_size(screen_rect.min, vec2(1.0, 1.0)),
+            callback: Arc::new(PaintCallbackFn::new(|_, _| ())),
+        }),
+    });
+    harness.render(output, 1.0)?;
+    let per_mesh_stats = harness.renderer.last_render_stats();
+    let per_mesh = harness.read_pixels()?;
+
+    assert!(batched_stats.mesh_count > 1, "the shapes are merged");
+    assert_eq!(batched_stats.callback_count, 0);
+    assert_eq!(per_mesh_stats.callback_count, 1);
+    assert_eq!(batched_stats.mesh_count, per_mesh_stats.mesh_count);
+    assert!(batched == per_mesh, "the readbacks differ");
+    Ok(())
+}
+
+/// A render target storing the linear output as is, like the common swap
+/// chain format `DXGI_FORMAT_R10G10B10A2_UNORM`, is reported once, unlike sRGB
+/// and float formats.
+#[test]
+fn linear_render_target_is_reported() -> egui_directx10::Result<()> {
+    for (format, reported) in [
+        (DXGI_FORMAT_R10G10B10A2_UNORM, true),
+        (DXGI_FORMAT_R8G8B8A8_UNORM, true),
+        (DXGI_FORMAT_R8G8B8A8_UNORM_SRGB, false),
+        (DXGI_FORMAT_R16G16B16A16_FLOAT, false),
+    ] {
+        let mut harness = Harness::new(format, Default::default())?;
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        harness.renderer.set_diagnostic_handler(Some(Box::new({
+            let warnings = warnings.clone();
+            move |warning: &str| warnings.borrow_mut().push(warning.to_owned())
+        })));
+        let rect = Rect::from_min_size(pos2(10.0, 10.0), vec2(20.0, 20.0));
+        for _ in 0..2 {
+            let output =
+                harness.run(1.0, |ctx| paint_rect(ctx, rect, Color32::RED));
+            harness
+                .render(egui_directx10::split_output(output).renderer, 1.0)?;
+        }
+        let count = warnings
+            .borrow()
+            .iter()
+            .filter(|warning| warning.contains("is not sRGB"))
+            .count();
+        assert_eq!(count, reported as usize, "warnings for {format:?}");
+    }
+    Ok(())
+}
+
+/// Pixels read back from a BGRA render target come in RGBA order like those of
+/// an RGBA one, while float render targets cannot be read back.
+#[test]
+fn read_pixels_returns_rgba() -> egui_directx10::Result<()> {
+    let rect = Rect::from_min_size(pos2(64.0, 64.0), vec2(64.0, 64.0));
+    for format in [
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+        DXGI_FORMAT_B8G8R8A8_UNORM_SRGB,
+    ] {
+        let mut harness = Harness::new(
+            format,
+            RendererConfig {
+                clear_color: Some([0.0, 0.0, 1.0, 1.0]),
+                ..Default::default()
+            },
+        )?;
+        let output =
+            harness.run(1.0, |ctx| paint_rect(ctx, rect, Color32::RED));
+        harness.render(egui_directx10::split_output(output).renderer, 1.0)?;
+        let pixels = harness.read_pixels()?;
+        assert_eq!(pixels.len(), (FRAME_WIDTH * FRAME_HEIGHT * 4) as usize);
+        assert_eq!(pixel(&pixels, 96, 96), [255, 0, 0, 255], "{format:?}");
+        assert_eq!(pixel(&pixels, 0, 0), [0, 0, 255, 255], "{format:?}");
+    }
+
+    let harness =
+        Harness::new(DXGI_FORMAT_R16G16B16A16_FLOAT, Default::default())?;
+    assert!(matches!(
+        harness.read_pixels(),
+        Err(egui_directx10::Error::InvalidArgument(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Tests of the lifecycle of the textures of the renderer.
+
+mod common;
+
+use common::{pixel, read_texture, Harness};
+
+use egui::{
+    epaint::ImageDelta, pos2, vec2, Color32, ColorImage, FontImage, ImageData,
+    LayerId, Rect, TextureId, TextureOptions, TexturesDelta,
+};
+
+use egui_directx10::{Renderer, RendererConfig};
+
+use windows::{
+    core::Interface,
+    Win32::Graphics::{Direct3D10::*, Dxgi::Common::*},
+};
+
+/// Create a small opaque green texture on the device of `renderer` and a
+/// view of it.
+fn create_native_texture(
+    renderer: &Renderer,
+) -> egui_directx10::Result<ID3D10ShaderResourceView> {
+    let (texture, render_target) =
+        renderer.create_offscreen_target(16, 16, DXGI_FORMAT_R8G8B8A8_UNORM)?;
+    let mut srv = None;
+    unsafe {
+        renderer
+            .device()
+            .ClearRenderTargetView(&render_target, &[0.0, 1.0, 0.0, 1.0]);
+        renderer.device().CreateShaderResourceView(
+            &texture,
+            None,
+            Some(&mut srv),
+        )
+    }?;
+    Ok(srv.unwrap())
+}
+
+/// Native textures lost by a device reset are reported, and can be supplied
+/// again under the same id.
+#[test]
+fn native_textures_are_resupplied_after_reset() -> egui_directx10::Result<()> {
+    let mut harness =
+        Harness::new(DXGI_FORMAT_R8G8B8A8_UNORM_SRGB, Default::default())?;
+    let srv = create_native_texture(&harness.renderer)?;
+    let tid = harness.renderer.register_native_texture(srv);
+
+    let device = egui_directx10::create_warp_device()?;
+    let lost = harness.renderer.reset_device(&device)?;
+    assert_eq!(lost, vec![tid]);
+    assert!(harness.renderer.get_texture_srv(tid).is_none());
+
+    let srv = create_native_texture(&harness.renderer)?;
+    assert!(harness.renderer.update_native_texture(tid, srv.clone()));
+    assert_eq!(harness.renderer.get_texture_srv(tid), Some(srv));
+    assert!(harness.renderer.reset_device(&device)?.contains(&tid));
+
+    harness.renderer.free_user_texture(tid);
+    assert!(harness.renderer.reset_device(&device)?.is_empty());
+    Ok(())
+}
+
+/// A native texture evicted to stay within the texture budget is not drawn
+/// anymore, instead of the mesh sampling it being drawn with whatever texture
+/// was bound before.
+#[test]
+fn evicted_texture_draws_nothing() -> egui_directx10::Result<()> {
+    let mut harness = Harness::new(
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+        RendererConfig {
+            clear_color: Some([0.0, 0.0, 0.0, 1.0]),
+            ..Default::default()
+        },
+    )?;
+    let srv = create_native_texture(&harness.renderer)?;
+    let tid = harness.renderer.register_native_texture(srv);
+    let ui = |ctx: &egui::Context| {
+        ctx.layer_painter(LayerId::background()).image(
+            tid,
+            Rect::from_min_size(pos2(64.0, 64.0), vec2(64.0, 64.0)),
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+    };
+
+    let output = harness.run(1.0, ui);
+    harness.render(egui_directx10::split_output(output).renderer, 1.0)?;
+    assert_eq!(harness.renderer.last_render_stats().mesh_count, 1);
+    assert_eq!(pixel(&harness.read_pixels()?, 96, 96), [0, 255, 0, 255]);
+
+    harness.renderer.set_texture_budget(Some(0));
+    let output = harness.run(1.0, ui);
+    harness.render(egui_directx10::split_output(output).renderer, 1.0)?;
+    assert!(harness.renderer.get_texture_srv(tid).is_none());
+    assert_eq!(harness.renderer.last_render_stats().mesh_count, 0);
+    assert_eq!(pixel(&harness.read_pixels()?, 96, 96), [0, 0, 0, 255]);
+    Ok(())
+}
+
+/// A texture delta setting `tid` to `image`, as a whole or at `pos`.
+fn set_image(
+    tid: TextureId,
+    pos: Option<[usize; 2]>,
+    image: impl Into<ImageData>,
+) -> TexturesDelta {
+    let image = image.into();
+    let delta = match pos {
+        Some(pos) => ImageDelta::partial(pos, image, TextureOptions::default()),
+        None => ImageDelta::full(image, TextureOptions::default()),
+    };
+    TexturesDelta {
+        set: vec![(tid, delta)],
+        free: Vec::new(),
+    }
+}
+
+/// Partial updates of a texture without a CPU mirror land on the GPU, each
+/// on top of the previous ones.
+#[test]
+fn partial_updates_without_mirror() -> egui_directx10::Result<()> {
+    let mut harness = Harness::new(
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+        RendererConfig {
+            texture_mirror: false,
+            ..Default::default()
+        },
+    )?;
+    let tid = TextureId::Managed(1);
+    let (width, height) = (4, 4);
+    let mut expected = vec![Color32::from_gray(100); width * height];
+    harness.renderer.upload_textures(
+        &harness.device,
+        set_image(
+            tid,
+            None,
+            ColorImage::new([width, height], Color32::from_gray(100)),
+        ),
+    )?;
+
+    // the second patch overlaps the first one at (2, 1).
+    for (pos, size, color) in [
+        ([0, 0], [3, 2], Color32::RED),
+        ([2, 1], [2, 3], Color32::BLUE),
+    ] {
+        harness.renderer.upload_textures(
+            &harness.device,
+            set_image(tid, Some(pos), ColorImage::new(size, color)),
+        )?;
+        for y in pos[1]..pos[1] + size[1] {
+            for x in pos[0]..pos[0] + size[0] {
+                expected[y * width + x] = color;
+            }
+        }
+    }
+
+    let srv = harness.renderer.get_texture_srv(tid).unwrap();
+    let (actual_width, actual_height, texels) =
+        read_texture(&harness.device, &srv)?;
+    assert_eq!((actual_width, actual_height), (width as _, height as _));
+    let expected = expected
+        .iter()
+        .flat_map(|color| color.to_array())
+        .collect::<Vec<_>>();
+    assert_eq!(texels, expected);
+    Ok(())
+}
+
+/// Get the address of the texture behind `srv`, which identifies the GPU
+/// resource as long as it is alive.
+fn resource_of(
+    srv: &ID3D10ShaderResourceView,
+) -> egui_directx10::Result<usize> {
+    Ok(unsafe { srv.GetResource() }?.as_raw() as usize)
+}
+
+/// The font atlas keeps its GPU resource and view while `egui` grows it with
+/// partial updates frame after frame, and gets a new one once `egui` sends it
+/// as a whole again, e.g. after a font change.
+#[test]
+fn font_atlas_resource_is_stable() -> egui_directx10::Result<()> {
+    let mut harness =
+        Harness::new(DXGI_FORMAT_R8G8B8A8_UNORM_SRGB, Default::default())?;
+    let tid = TextureId::default();
+    harness.renderer.upload_textures(
+        &harness.device,
+        set_image(tid, None, FontImage::new([64, 64])),
+    )?;
+    let srv = harness.renderer.get_texture_srv(tid).unwrap();
+    let resource = resource_of(&srv)?;
+
+    // glyphs rasterized one frame after another, as egui does.
+    for i in 0..8 {
+        let glyph = FontImage {
+            size: [8, 8],
+            pixels: vec![1.0; 64],
+        };
+        harness.renderer.upload_textures(
+            &harness.device,
+            set_image(tid, Some([i * 8, i % 2 * 8]), glyph),
+        )?;
+        let current = harness.renderer.get_texture_srv(tid).unwrap();
+        assert_eq!(current.as_raw(), srv.as_raw(), "view after frame {i}");
+        assert_eq!(resource_of(&current)?, resource, "texture after frame {i}");
+    }
+
+    // `srv` keeps the previous texture alive, so its address is not reused.
+    harness.renderer.upload_textures(
+        &harness.device,
+        set_image(tid, None, FontImage::new([128, 128])),
+    )?;
+    let current = harness.renderer.get_texture_srv(tid).unwrap();
+    assert_ne!(current.as_raw(), srv.as_raw());
+    assert_ne!(resource_of(&current)?, resource);
+    Ok(())
+}